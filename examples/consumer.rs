@@ -1,4 +1,5 @@
 use rdkafka_sys::kafka::config::Config;
+use rdkafka_sys::kafka::{ConsumerEvent, ShutdownHandle};
 use std::{thread, time};
 
 fn main() {
@@ -19,11 +20,12 @@ fn main() {
 fn run(cons_conf: Config, prod_conf: Config) {
     let mut consumer = cons_conf.build_consumer().unwrap();
     consumer.subscribe(&vec!["some_topic", "2nd_topic"]);
+    let shutdown = ShutdownHandle::new();
 
-    let mut producer = prod_conf.build_producer().unwrap();
-    producer.set_topics(&["some_topic", "2nd_topic"]);
+    let producer = prod_conf.build_producer().unwrap();
     let t = thread::Builder::new()
         .name(String::from("producer_thread"));
+    let producer_shutdown = shutdown.clone();
     let handle = t.spawn(move||{
         for i in 1..=5 {
             let payload = format!("Message: {}", i);
@@ -34,15 +36,22 @@ fn run(cons_conf: Config, prod_conf: Config) {
             thread::sleep(time::Duration::from_secs(1));
         }
         producer.flush(1000);
+
+        // give the consumer some time to catch up before stopping it
+        thread::sleep(time::Duration::from_secs(2));
+        producer_shutdown.shutdown();
     }).unwrap();
 
-    for _ in 0..7 {
-        println!("Waiting for message...");
-        for message in consumer.get_messages(1000).unwrap() {
-            let m = message.unwrap();
-            println!("Message: {}", m.read().unwrap());
+    println!("Waiting for message...");
+    for event in consumer.stream(shutdown) {
+        match event.unwrap() {
+            ConsumerEvent::Message(m) => {
+                println!("Message from {}: {}", m.topic, m.read().unwrap())
+            }
+            ConsumerEvent::PartitionEof { topic, partition, .. } => {
+                println!("Reached end of {} [{}]", topic, partition)
+            }
         }
-        thread::sleep(time::Duration::from_secs(1));
     }
 
     handle.join().unwrap();
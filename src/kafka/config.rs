@@ -1,10 +1,19 @@
 use super::consumer::Consumer;
+use super::consumer_context::{
+    offset_commit_cb, rebalance_cb, ConsumerContext, DefaultConsumerContext,
+};
+use super::delivery::delivery_report_cb;
+use super::future_producer::FutureProducer;
 use super::producer::Producer;
+use super::producer_context::{producer_error_cb, DefaultProducerContext, ProducerContext};
+use super::threaded_producer::ThreadedProducer;
 use std::collections::HashMap;
-use std::ffi::{CStr, CString};
+use std::ffi::{c_void, CStr, CString};
 
 use crate::bindings::{
-    rd_kafka_conf_new, rd_kafka_conf_s, rd_kafka_conf_set, rd_kafka_new,
+    rd_kafka_conf_new, rd_kafka_conf_s, rd_kafka_conf_set, rd_kafka_conf_set_dr_msg_cb,
+    rd_kafka_conf_set_error_cb, rd_kafka_conf_set_offset_commit_cb, rd_kafka_conf_set_opaque,
+    rd_kafka_conf_set_rebalance_cb, rd_kafka_new, rd_kafka_poll_set_consumer,
     rd_kafka_type_t_RD_KAFKA_CONSUMER, rd_kafka_type_t_RD_KAFKA_PRODUCER,
 };
 
@@ -26,9 +35,22 @@ impl Config {
         self
     }
 
-    pub fn build_consumer(mut self) -> Result<Consumer, ConfigError> {
+    pub fn build_consumer(self) -> Result<Consumer, ConfigError> {
+        self.build_consumer_with_context(DefaultConsumerContext)
+    }
+
+    /// Builds a consumer whose events are reported to `context`
+    pub fn build_consumer_with_context<C>(mut self, context: C) -> Result<Consumer, ConfigError>
+    where
+        C: ConsumerContext + 'static,
+    {
         let conf = self.create_rdkafka_conf()?;
+        let context: Box<Box<dyn ConsumerContext>> = Box::new(Box::new(context));
         unsafe {
+            set_context_opaque(conf, &*context);
+            rd_kafka_conf_set_offset_commit_cb(conf, Some(offset_commit_cb));
+            rd_kafka_conf_set_rebalance_cb(conf, Some(rebalance_cb));
+
             let err = CString::new(String::with_capacity(512)).unwrap().into_raw();
             let rk = rd_kafka_new(rd_kafka_type_t_RD_KAFKA_CONSUMER, conf, err, 512);
             let err = CStr::from_ptr(err).to_str().unwrap();
@@ -39,13 +61,29 @@ impl Config {
                     reason: err.to_string(),
                 });
             }
-            Ok(Consumer::new(rk))
+
+            // serve commit callbacks and group events from consumer polls
+            rd_kafka_poll_set_consumer(rk);
+            Ok(Consumer::with_context(rk, context))
         }
     }
 
-    pub fn build_producer(mut self) -> Result<Producer, ConfigError> {
+    pub fn build_producer(self) -> Result<Producer, ConfigError> {
+        self.build_producer_with_context(DefaultProducerContext)
+    }
+
+    /// Builds a producer whose events are reported to `context`
+    pub fn build_producer_with_context<C>(mut self, context: C) -> Result<Producer, ConfigError>
+    where
+        C: ProducerContext + 'static,
+    {
         let conf = self.create_rdkafka_conf()?;
+        let context: Box<Box<dyn ProducerContext>> = Box::new(Box::new(context));
         unsafe {
+            set_context_opaque(conf, &*context);
+            rd_kafka_conf_set_dr_msg_cb(conf, Some(delivery_report_cb));
+            rd_kafka_conf_set_error_cb(conf, Some(producer_error_cb));
+
             let err = CString::new(String::with_capacity(512)).unwrap().into_raw();
             let rk = rd_kafka_new(rd_kafka_type_t_RD_KAFKA_PRODUCER, conf, err, 512);
             let err = CStr::from_ptr(err).to_str().unwrap();
@@ -56,10 +94,44 @@ impl Config {
                     reason: err.to_string(),
                 });
             }
-            Ok(Producer::new(rk))
+            Ok(Producer::with_context(rk, context))
         }
     }
 
+    /// Builds a producer polled by a background thread
+    pub fn build_threaded_producer(self) -> Result<ThreadedProducer, ConfigError> {
+        self.build_threaded_producer_with_context(DefaultProducerContext)
+    }
+
+    /// Same as `build_threaded_producer`, reporting errors to `context`
+    pub fn build_threaded_producer_with_context<C>(
+        self,
+        context: C,
+    ) -> Result<ThreadedProducer, ConfigError>
+    where
+        C: ProducerContext + 'static,
+    {
+        let producer = self.build_producer_with_context(context)?;
+        Ok(ThreadedProducer::new(producer))
+    }
+
+    /// Builds a producer whose sends resolve on delivery
+    pub fn build_future_producer(self) -> Result<FutureProducer, ConfigError> {
+        self.build_future_producer_with_context(DefaultProducerContext)
+    }
+
+    /// Same as `build_future_producer`, reporting errors to `context`
+    pub fn build_future_producer_with_context<C>(
+        self,
+        context: C,
+    ) -> Result<FutureProducer, ConfigError>
+    where
+        C: ProducerContext + 'static,
+    {
+        let producer = self.build_producer_with_context(context)?;
+        Ok(FutureProducer::new(producer))
+    }
+
     fn create_rdkafka_conf(&mut self) -> Result<*mut rd_kafka_conf_s, ConfigError> {
         unsafe {
             let conf = rd_kafka_conf_new();
@@ -80,6 +152,13 @@ impl Config {
     }
 }
 
+/// Points the conf opaque, which librdkafka passes to every callback, at the
+/// client's `Box<dyn ...Context>`. The outer box gives it a stable address;
+/// the built `Consumer`/`Producer` owns it so it outlives the client handle.
+unsafe fn set_context_opaque<T>(conf: *mut rd_kafka_conf_s, context: &T) {
+    rd_kafka_conf_set_opaque(conf, context as *const T as *mut c_void);
+}
+
 fn cstr(s: &str) -> CString {
     CString::new(s).unwrap()
 }
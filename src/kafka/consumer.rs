@@ -1,43 +1,399 @@
-use super::message::Messages;
+use super::consumer_context::{ConsumerContext, DefaultConsumerContext};
+use super::error::{KafkaError, KafkaErrorCode};
+use super::message::{
+    self, BorrowedMessage, MessageError, MessagePosition, MessageStream, Messages, ShutdownHandle,
+};
+use super::offset::Offset;
+use super::topic_partition_list::TopicPartitionList;
 use crate::bindings::{
-    rd_kafka_consumer_close, rd_kafka_destroy, rd_kafka_s, rd_kafka_subscribe,
-    rd_kafka_topic_partition_list_add, rd_kafka_topic_partition_list_destroy,
-    rd_kafka_topic_partition_list_new, rd_kafka_topic_partition_list_t,
+    rd_kafka_assign, rd_kafka_assignment, rd_kafka_commit, rd_kafka_committed,
+    rd_kafka_consume_batch_queue, rd_kafka_consumer_close, rd_kafka_destroy,
+    rd_kafka_get_watermark_offsets, rd_kafka_last_error, rd_kafka_message_t,
+    rd_kafka_offsets_for_times, rd_kafka_offsets_store, rd_kafka_pause_partitions,
+    rd_kafka_position, rd_kafka_query_watermark_offsets, rd_kafka_queue_destroy,
+    rd_kafka_queue_get_consumer, rd_kafka_queue_t, rd_kafka_resume_partitions, rd_kafka_s,
+    rd_kafka_seek, rd_kafka_subscribe, rd_kafka_topic_destroy, rd_kafka_topic_new,
+    rd_kafka_topic_partition_list_t,
 };
-
+use std::collections::HashMap;
 use std::ffi::CString;
+use std::os::raw::c_int;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommitMode {
+    /// Block until the broker acknowledged the commit
+    Sync,
+    /// Return immediately, the result goes to `ConsumerContext::commit_callback`
+    Async,
+}
 
-#[derive(Debug, Clone)]
 pub struct Consumer {
     rk: *mut rd_kafka_s,
-    topic_partition: *mut rd_kafka_topic_partition_list_t,
+    subscribed: bool,
+    assigned: bool,
+    // null when the consumer has no group
+    queue: *mut rd_kafka_queue_t,
+    _context: Box<Box<dyn ConsumerContext>>,
 }
 
 impl Consumer {
     pub fn new(rk: *mut rd_kafka_s) -> Consumer {
+        Consumer::with_context(rk, Box::new(Box::new(DefaultConsumerContext)))
+    }
+
+    pub(crate) fn with_context(
+        rk: *mut rd_kafka_s,
+        context: Box<Box<dyn ConsumerContext>>,
+    ) -> Consumer {
         Consumer {
             rk,
-            topic_partition: std::ptr::null_mut(),
+            subscribed: false,
+            assigned: false,
+            queue: unsafe { rd_kafka_queue_get_consumer(rk) },
+            _context: context,
         }
     }
 
+    /// Consumer queue, null when the consumer has no group
+    #[cfg(feature = "async")]
+    pub(crate) fn queue(&self) -> *mut rd_kafka_queue_t {
+        self.queue
+    }
+
+    /// Joins the consumer group on `topics`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a topic name contains a NUL byte.
     pub fn subscribe(&mut self, topics: &[&str]) {
+        let mut list = TopicPartitionList::with_capacity(topics.len());
+        for topic in topics {
+            list.add(topic, -1).expect("topic name contains a NUL byte");
+        }
+
+        unsafe {
+            rd_kafka_subscribe(self.rk, list.ptr());
+        }
+        self.subscribed = true;
+    }
+
+    /// Reads exactly the given partitions, starting at each entry's offset,
+    /// without joining the consumer group. Replaces any previous assignment.
+    pub fn assign(&mut self, partitions: &TopicPartitionList) -> Result<(), ConsumerError> {
+        unsafe {
+            let err_code = rd_kafka_assign(self.rk, partitions.ptr());
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::AssignError(err));
+            }
+        }
+        self.assigned = !partitions.is_empty();
+        Ok(())
+    }
+
+    pub fn unassign(&mut self) -> Result<(), ConsumerError> {
+        unsafe {
+            let err_code = rd_kafka_assign(self.rk, std::ptr::null_mut());
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::AssignError(err));
+            }
+        }
+        self.assigned = false;
+        Ok(())
+    }
+
+    /// Returns the current partition assignment, whether it was set with
+    /// `assign` or by the consumer group
+    pub fn assignment(&self) -> Result<TopicPartitionList, ConsumerError> {
         unsafe {
-            self.destroy_topic_partition();
+            let mut partitions: *mut rd_kafka_topic_partition_list_t = std::ptr::null_mut();
+            let err_code = rd_kafka_assignment(self.rk, &mut partitions);
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::AssignError(err));
+            }
+            Ok(TopicPartitionList::from_ptr(partitions))
+        }
+    }
+
+    /// Commits the offsets in `offsets`. Each offset should be the one of the
+    /// next message to read, i.e. last processed offset + 1.
+    pub fn commit(
+        &self,
+        offsets: &TopicPartitionList,
+        mode: CommitMode,
+    ) -> Result<(), ConsumerError> {
+        self.commit_list(offsets.ptr(), mode)
+    }
+
+    /// Commits the position right after `message`
+    pub fn commit_message<M>(&self, message: &M, mode: CommitMode) -> Result<(), ConsumerError>
+    where
+        M: MessagePosition,
+    {
+        let mut offsets = TopicPartitionList::with_capacity(1);
+        let next = Offset::Offset(message.offset() + 1);
+        offsets
+            .set_offset(message.topic(), message.partition(), next)
+            .map_err(ConsumerError::CommitError)?;
+        self.commit(&offsets, mode)
+    }
 
-            let top_par = rd_kafka_topic_partition_list_new(topics.len() as i32);
-            for topic in topics {
-                let topic = CString::new(String::from(*topic)).unwrap().into_raw();
-                rd_kafka_topic_partition_list_add(top_par, topic, -1);
+    /// Commits the current position of every assigned partition
+    pub fn commit_consumer_state(&self, mode: CommitMode) -> Result<(), ConsumerError> {
+        self.commit_list(std::ptr::null_mut(), mode)
+    }
+
+    fn commit_list(
+        &self,
+        offsets: *mut rd_kafka_topic_partition_list_t,
+        mode: CommitMode,
+    ) -> Result<(), ConsumerError> {
+        unsafe {
+            let err_code = rd_kafka_commit(self.rk, offsets, (mode == CommitMode::Async) as c_int);
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::CommitError(err));
             }
+            Ok(())
+        }
+    }
 
-            rd_kafka_subscribe(self.rk, top_par);
-            self.topic_partition = top_par;
+    /// Marks `message` as processed, so the next automatic or
+    /// `commit_consumer_state` commit includes it. Requires
+    /// `enable.auto.offset.store=false`.
+    pub fn store_offset<M>(&self, message: &M) -> Result<(), ConsumerError>
+    where
+        M: MessagePosition,
+    {
+        let mut offsets = TopicPartitionList::with_capacity(1);
+        let next = Offset::Offset(message.offset() + 1);
+        offsets
+            .set_offset(message.topic(), message.partition(), next)
+            .map_err(ConsumerError::StoreError)?;
+        self.store_offsets(&mut offsets)
+    }
+
+    /// Stores the given offsets for the next commit. Failed entries keep their
+    /// error in the list, the first one is returned.
+    pub fn store_offsets(&self, offsets: &mut TopicPartitionList) -> Result<(), ConsumerError> {
+        unsafe {
+            let err_code = rd_kafka_offsets_store(self.rk, offsets.ptr());
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::StoreError(err));
+            }
+        }
+        match offsets.first_error() {
+            Some(err) => Err(ConsumerError::StoreError(err)),
+            None => Ok(()),
         }
     }
 
+    /// Moves the fetch position of an assigned partition. Blocks up to
+    /// `timeout_ms` for the seek to finish; 0 returns right away.
+    pub fn seek(
+        &self,
+        topic: &str,
+        partition: i32,
+        offset: Offset,
+        timeout_ms: i32,
+    ) -> Result<(), ConsumerError> {
+        let topic = match CString::new(topic) {
+            Ok(topic) => topic,
+            Err(_) => {
+                let err = KafkaError::new(KafkaErrorCode::InvalidArg);
+                return Err(ConsumerError::SeekError(err));
+            }
+        };
+        unsafe {
+            // returns the existing handle when the topic is already known
+            let rkt = rd_kafka_topic_new(self.rk, topic.as_ptr(), std::ptr::null_mut());
+            if rkt.is_null() {
+                let err = KafkaError::new(rd_kafka_last_error().into());
+                return Err(ConsumerError::SeekError(err));
+            }
+            let err_code = rd_kafka_seek(rkt, partition, offset.to_raw(), timeout_ms);
+            rd_kafka_topic_destroy(rkt);
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::SeekError(err));
+            }
+            Ok(())
+        }
+    }
+
+    /// Stops fetching from the given partitions until they are resumed.
+    /// Failed entries keep their error in the list, the first one is returned.
+    pub fn pause(&self, partitions: &mut TopicPartitionList) -> Result<(), ConsumerError> {
+        let err_code = unsafe { rd_kafka_pause_partitions(self.rk, partitions.ptr()) };
+        if let Some(err) = super::get_error(err_code).or_else(|| partitions.first_error()) {
+            return Err(ConsumerError::PauseError(err));
+        }
+        Ok(())
+    }
+
+    pub fn resume(&self, partitions: &mut TopicPartitionList) -> Result<(), ConsumerError> {
+        let err_code = unsafe { rd_kafka_resume_partitions(self.rk, partitions.ptr()) };
+        if let Some(err) = super::get_error(err_code).or_else(|| partitions.first_error()) {
+            return Err(ConsumerError::ResumeError(err));
+        }
+        Ok(())
+    }
+
+    /// Asks the partition leader for the (low, high) watermark offsets
+    pub fn fetch_watermarks(
+        &self,
+        topic: &str,
+        partition: i32,
+        timeout_ms: i32,
+    ) -> Result<(i64, i64), ConsumerError> {
+        let topic = query_topic(topic)?;
+        let (mut low, mut high) = (-1, -1);
+        unsafe {
+            let err_code = rd_kafka_query_watermark_offsets(
+                self.rk,
+                topic.as_ptr(),
+                partition,
+                &mut low,
+                &mut high,
+                timeout_ms,
+            );
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::QueryError(err));
+            }
+        }
+        Ok((low, high))
+    }
+
+    /// Returns the (low, high) watermark offsets last seen in fetch
+    /// responses, without a broker round trip. Either may be -1 if unknown.
+    pub fn get_watermark_offsets(
+        &self,
+        topic: &str,
+        partition: i32,
+    ) -> Result<(i64, i64), ConsumerError> {
+        let topic = query_topic(topic)?;
+        let (mut low, mut high) = (-1, -1);
+        unsafe {
+            let err_code = rd_kafka_get_watermark_offsets(
+                self.rk,
+                topic.as_ptr(),
+                partition,
+                &mut low,
+                &mut high,
+            );
+            if let Some(err) = super::get_error(err_code) {
+                return Err(ConsumerError::QueryError(err));
+            }
+        }
+        Ok((low, high))
+    }
+
+    /// Fetches the committed offsets of the given partitions from the group
+    /// coordinator
+    pub fn committed(
+        &self,
+        partitions: &TopicPartitionList,
+        timeout_ms: i32,
+    ) -> Result<TopicPartitionList, ConsumerError> {
+        let offsets = partitions.clone();
+        let err_code = unsafe { rd_kafka_committed(self.rk, offsets.ptr(), timeout_ms) };
+        if let Some(err) = super::get_error(err_code) {
+            return Err(ConsumerError::QueryError(err));
+        }
+        Ok(offsets)
+    }
+
+    /// Returns the offset of the next message to be consumed for each of the
+    /// given partitions
+    pub fn position(
+        &self,
+        partitions: &TopicPartitionList,
+    ) -> Result<TopicPartitionList, ConsumerError> {
+        let offsets = partitions.clone();
+        let err_code = unsafe { rd_kafka_position(self.rk, offsets.ptr()) };
+        if let Some(err) = super::get_error(err_code) {
+            return Err(ConsumerError::QueryError(err));
+        }
+        Ok(offsets)
+    }
+
+    /// Looks up, for each partition, the earliest offset whose timestamp is at
+    /// or after the given time. The result can be passed to `assign`; entries
+    /// past the last message get `Offset::End`, failed ones keep their error.
+    /// Times before the epoch are rejected with `InvalidArg`.
+    pub fn offsets_for_times(
+        &self,
+        timestamps: HashMap<(String, i32), SystemTime>,
+        timeout_ms: i32,
+    ) -> Result<TopicPartitionList, ConsumerError> {
+        let mut offsets = TopicPartitionList::with_capacity(timestamps.len());
+        for ((topic, partition), time) in timestamps {
+            // librdkafka reads negative timestamps as logical offsets
+            let ms = match time.duration_since(UNIX_EPOCH) {
+                Ok(since_epoch) => since_epoch.as_millis(),
+                Err(_) => {
+                    let err = KafkaError::new(KafkaErrorCode::InvalidArg);
+                    return Err(ConsumerError::QueryError(err));
+                }
+            };
+            offsets
+                .set_offset(&topic, partition, Offset::Offset(ms as i64))
+                .map_err(ConsumerError::QueryError)?;
+        }
+
+        let err_code = unsafe { rd_kafka_offsets_for_times(self.rk, offsets.ptr(), timeout_ms) };
+        if let Some(err) = super::get_error(err_code) {
+            return Err(ConsumerError::QueryError(err));
+        }
+        Ok(offsets)
+    }
+
+    /// Polls a single message without copying it. Returns `None` when no
+    /// message arrived within `timeout_ms`.
+    pub fn poll(&self, timeout_ms: i32) -> Option<Result<BorrowedMessage<'_>, MessageError>> {
+        unsafe { message::poll_message(self.rk, timeout_ms) }
+    }
+
+    /// Reads up to `max_messages` from the consumer queue in one call,
+    /// waiting at most `timeout_ms` for the first one. Messages are not
+    /// copied; error events are returned in place.
+    pub fn consume_batch(
+        &self,
+        max_messages: usize,
+        timeout_ms: i32,
+    ) -> Result<Vec<Result<BorrowedMessage<'_>, MessageError>>, ConsumerError> {
+        if self.queue.is_null() {
+            let err = KafkaError::new(KafkaErrorCode::UnknownGroup);
+            return Err(ConsumerError::ConsumeError(err));
+        }
+
+        let mut msgs: Vec<*mut rd_kafka_message_t> = Vec::with_capacity(max_messages);
+        unsafe {
+            let cnt = rd_kafka_consume_batch_queue(
+                self.queue,
+                timeout_ms,
+                msgs.as_mut_ptr(),
+                max_messages as u64,
+            );
+            if cnt < 0 {
+                let err = KafkaError::new(rd_kafka_last_error().into());
+                return Err(ConsumerError::ConsumeError(err));
+            }
+            msgs.set_len(cnt as usize);
+
+            Ok(msgs
+                .into_iter()
+                .map(|msg| message::take_message(msg))
+                .collect())
+        }
+    }
+
+    /// Iterates over messages until `shutdown` is triggered, waiting as long
+    /// as needed for each one. Partition EOFs are reported as their own event.
+    pub fn stream(&self, shutdown: ShutdownHandle) -> MessageStream<'_> {
+        MessageStream::new(self.rk, shutdown)
+    }
+
     pub fn get_messages(&self, poll_timeout_ms: i32) -> Result<Messages, ConsumerError> {
-        if self.topic_partition.is_null() {
+        if !self.subscribed && !self.assigned {
             return Err(ConsumerError::NoTopic);
         }
 
@@ -46,20 +402,21 @@ impl Consumer {
 
     fn close(&self) -> Result<(), ConsumerError> {
         unsafe {
-            self.destroy_topic_partition();
             let err_code = rd_kafka_consumer_close(self.rk);
-            if let Some(err) = super::get_error_str(err_code) {
+            if let Some(err) = super::get_error(err_code) {
                 return Err(ConsumerError::CloseError(err));
             }
             Ok(())
         }
     }
+}
 
-    fn destroy_topic_partition(&self) {
-        if !self.topic_partition.is_null() {
-            unsafe { rd_kafka_topic_partition_list_destroy(self.topic_partition) }
-        }
-    }
+/// Topic name for a query, which librdkafka cannot take with an interior NUL
+fn query_topic(topic: &str) -> Result<CString, ConsumerError> {
+    CString::new(topic).map_err(|_| {
+        let err = KafkaError::new(KafkaErrorCode::InvalidArg);
+        ConsumerError::QueryError(err)
+    })
 }
 
 impl Drop for Consumer {
@@ -69,16 +426,37 @@ impl Drop for Consumer {
         }
 
         unsafe {
+            if !self.queue.is_null() {
+                rd_kafka_queue_destroy(self.queue);
+            }
             rd_kafka_destroy(self.rk);
         }
     }
 }
 
 use std::fmt;
+impl fmt::Debug for Consumer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Consumer")
+            .field("rk", &self.rk)
+            .field("subscribed", &self.subscribed)
+            .field("assigned", &self.assigned)
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ConsumerError {
     NoTopic,
-    CloseError(String),
+    CloseError(KafkaError),
+    AssignError(KafkaError),
+    CommitError(KafkaError),
+    StoreError(KafkaError),
+    SeekError(KafkaError),
+    PauseError(KafkaError),
+    ResumeError(KafkaError),
+    QueryError(KafkaError),
+    ConsumeError(KafkaError),
 }
 
 impl fmt::Display for ConsumerError {
@@ -89,7 +467,18 @@ impl fmt::Display for ConsumerError {
 
 impl std::error::Error for ConsumerError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        match self {
+            ConsumerError::CloseError(err)
+            | ConsumerError::AssignError(err)
+            | ConsumerError::CommitError(err)
+            | ConsumerError::StoreError(err)
+            | ConsumerError::SeekError(err)
+            | ConsumerError::PauseError(err)
+            | ConsumerError::ResumeError(err)
+            | ConsumerError::QueryError(err)
+            | ConsumerError::ConsumeError(err) => Some(err),
+            _ => None,
+        }
     }
 }
 
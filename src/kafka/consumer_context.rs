@@ -0,0 +1,96 @@
+use super::error::KafkaError;
+use super::topic_partition_list::TopicPartitionList;
+use crate::bindings::{
+    self, rd_kafka_assign, rd_kafka_resp_err_t, rd_kafka_t, rd_kafka_topic_partition_list_copy,
+    rd_kafka_topic_partition_list_t,
+};
+use std::ffi::c_void;
+
+/// Consumer group rebalance event
+#[derive(Debug)]
+pub enum Rebalance {
+    /// Partitions newly assigned to this consumer
+    Assign(TopicPartitionList),
+    /// Partitions taken away from this consumer
+    Revoke(TopicPartitionList),
+    /// The rebalance failed, the current assignment is dropped
+    Error(KafkaError),
+}
+
+/// Hooks into consumer events. Callbacks run on the thread polling the
+/// consumer, so they should return quickly. A panicking hook aborts the
+/// process, since it is called from librdkafka.
+pub trait ConsumerContext: Send + Sync {
+    /// Called before the assignment changes. On `Revoke` the partitions are
+    /// still owned, so this is the place to flush state and store offsets.
+    fn pre_rebalance(&self, _rebalance: &Rebalance) {}
+
+    /// Called once the new assignment is in place
+    fn post_rebalance(&self, _rebalance: &Rebalance) {}
+
+    /// Called with the result of every offset commit, automatic or explicit
+    fn commit_callback(&self, _result: Result<(), KafkaError>, _offsets: &TopicPartitionList) {}
+
+    /// Called for errors not tied to a call, e.g. failing to apply the
+    /// assignment of a rebalance
+    fn error(&self, _error: &KafkaError) {}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DefaultConsumerContext;
+
+impl ConsumerContext for DefaultConsumerContext {}
+
+/// Copies a list owned by librdkafka, which may be null
+unsafe fn copy_list(list: *const rd_kafka_topic_partition_list_t) -> TopicPartitionList {
+    match list.is_null() {
+        true => TopicPartitionList::new(),
+        false => TopicPartitionList::from_ptr(rd_kafka_topic_partition_list_copy(list)),
+    }
+}
+
+/// `rebalance_cb` registered on consumers built from `Config`. Applies the
+/// assignment change between the two context hooks.
+pub(crate) unsafe extern "C" fn rebalance_cb(
+    rk: *mut rd_kafka_t,
+    err: rd_kafka_resp_err_t,
+    partitions: *mut rd_kafka_topic_partition_list_t,
+    opaque: *mut c_void,
+) {
+    let context = &*(opaque as *const Box<dyn ConsumerContext>);
+    let rebalance = match err {
+        bindings::rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__ASSIGN_PARTITIONS => {
+            Rebalance::Assign(copy_list(partitions))
+        }
+        bindings::rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__REVOKE_PARTITIONS => {
+            Rebalance::Revoke(copy_list(partitions))
+        }
+        _ => Rebalance::Error(KafkaError::new(err.into())),
+    };
+
+    super::call_hook(|| context.pre_rebalance(&rebalance));
+    let assign_err = match rebalance {
+        Rebalance::Assign(_) => rd_kafka_assign(rk, partitions),
+        _ => rd_kafka_assign(rk, std::ptr::null_mut()),
+    };
+    if let Some(err) = super::get_error(assign_err) {
+        super::call_hook(|| context.error(&err));
+    }
+    super::call_hook(|| context.post_rebalance(&rebalance));
+}
+
+/// `offset_commit_cb` registered on consumers built from `Config`
+pub(crate) unsafe extern "C" fn offset_commit_cb(
+    _rk: *mut rd_kafka_t,
+    err: rd_kafka_resp_err_t,
+    offsets: *mut rd_kafka_topic_partition_list_t,
+    opaque: *mut c_void,
+) {
+    let context = &*(opaque as *const Box<dyn ConsumerContext>);
+    let result = match super::get_error(err) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    };
+    let offsets = copy_list(offsets);
+    super::call_hook(|| context.commit_callback(result, &offsets));
+}
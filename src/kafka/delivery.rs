@@ -0,0 +1,145 @@
+use super::producer::ProducerError;
+use crate::bindings::{rd_kafka_message_t, rd_kafka_t};
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// Partition and offset the message was written to, or the reason it was not
+pub type DeliveryResult = Result<(i32, i64), ProducerError>;
+
+struct DeliveryState {
+    inner: Mutex<Inner>,
+    cond: Condvar,
+    opaque: Option<usize>,
+}
+
+struct Inner {
+    result: Option<DeliveryResult>,
+    waker: Option<Waker>,
+    // payload librdkafka references without a copy, kept until the report
+    held: Option<Box<dyn Any + Send>>,
+}
+
+impl DeliveryState {
+    fn complete(&self, result: DeliveryResult) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.result = Some(result);
+        inner.held = None;
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+        self.cond.notify_all();
+    }
+}
+
+/// Handle to the delivery report of a single message. Reports are only
+/// served while the producer is being polled.
+///
+/// The token can either be waited on from a blocking context or awaited
+/// as a `Future`.
+pub struct DeliveryToken {
+    state: Arc<DeliveryState>,
+}
+
+impl DeliveryToken {
+    /// Creates a token together with the opaque to pass along with the
+    /// message. The opaque is released by `delivery_report_cb`, or by
+    /// `DeliveryToken::release` when producing fails.
+    pub(crate) fn new(opaque: Option<usize>) -> (DeliveryToken, *mut c_void) {
+        let state = Arc::new(DeliveryState {
+            inner: Mutex::new(Inner {
+                result: None,
+                waker: None,
+                held: None,
+            }),
+            cond: Condvar::new(),
+            opaque,
+        });
+        let opaque = Arc::into_raw(state.clone()) as *mut c_void;
+        (DeliveryToken { state }, opaque)
+    }
+
+    pub(crate) unsafe fn release(opaque: *mut c_void) {
+        drop(Arc::from_raw(opaque as *const DeliveryState));
+    }
+
+    /// Keeps `value` alive until the delivery report is served
+    pub(crate) fn hold(&self, value: Box<dyn Any + Send>) {
+        self.state.inner.lock().unwrap().held = Some(value);
+    }
+
+    pub(crate) fn take_held(&self) -> Option<Box<dyn Any + Send>> {
+        self.state.inner.lock().unwrap().held.take()
+    }
+
+    /// Opaque the record was sent with
+    pub fn opaque(&self) -> Option<usize> {
+        self.state.opaque
+    }
+
+    /// Blocks until the delivery report arrives
+    pub fn wait(self) -> DeliveryResult {
+        let mut inner = self.state.inner.lock().unwrap();
+        loop {
+            if let Some(res) = inner.result.take() {
+                return res;
+            }
+            inner = self.state.cond.wait(inner).unwrap();
+        }
+    }
+
+    /// Blocks until the delivery report arrives or `timeout` elapses
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<DeliveryResult> {
+        let inner = self.state.inner.lock().unwrap();
+        let (inner, _) = self
+            .state
+            .cond
+            .wait_timeout_while(inner, timeout, |i| i.result.is_none())
+            .unwrap();
+        inner.result.clone()
+    }
+
+    /// Returns the delivery report if it has already arrived
+    pub fn try_get(&self) -> Option<DeliveryResult> {
+        self.state.inner.lock().unwrap().result.clone()
+    }
+}
+
+impl Future for DeliveryToken {
+    type Output = DeliveryResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.state.inner.lock().unwrap();
+        match inner.result.take() {
+            Some(res) => Poll::Ready(res),
+            None => {
+                inner.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// `dr_msg_cb` registered on every producer built from `Config`
+pub(crate) unsafe extern "C" fn delivery_report_cb(
+    rk: *mut rd_kafka_t,
+    rkmessage: *const rd_kafka_message_t,
+    _opaque: *mut c_void,
+) {
+    let msg = &*rkmessage;
+    if msg._private.is_null() {
+        return;
+    }
+
+    let state = Arc::from_raw(msg._private as *const DeliveryState);
+    let result = match super::get_error(msg.err) {
+        Some(err) => Err(ProducerError::DeliveryError(err.resolve_fatal(rk))),
+        None => Ok((msg.partition, msg.offset)),
+    };
+    state.complete(result);
+}
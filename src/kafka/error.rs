@@ -0,0 +1,359 @@
+use crate::bindings::{
+    self, rd_kafka_err2name, rd_kafka_err2str, rd_kafka_error_code, rd_kafka_error_destroy,
+    rd_kafka_error_is_fatal, rd_kafka_error_is_retriable, rd_kafka_error_string, rd_kafka_error_t,
+    rd_kafka_error_txn_requires_abort, rd_kafka_fatal_error, rd_kafka_resp_err_t, rd_kafka_t,
+};
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+
+macro_rules! error_codes {
+    ($($variant:ident => $code:ident,)*) => {
+        /// Typed `rd_kafka_resp_err_t`. Codes unknown to these bindings are
+        /// kept in `Other`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KafkaErrorCode {
+            $($variant,)*
+            Other(rd_kafka_resp_err_t),
+        }
+
+        impl From<rd_kafka_resp_err_t> for KafkaErrorCode {
+            fn from(code: rd_kafka_resp_err_t) -> KafkaErrorCode {
+                match code {
+                    $(bindings::$code => KafkaErrorCode::$variant,)*
+                    other => KafkaErrorCode::Other(other),
+                }
+            }
+        }
+
+        impl From<KafkaErrorCode> for rd_kafka_resp_err_t {
+            fn from(code: KafkaErrorCode) -> rd_kafka_resp_err_t {
+                match code {
+                    $(KafkaErrorCode::$variant => bindings::$code,)*
+                    KafkaErrorCode::Other(other) => other,
+                }
+            }
+        }
+
+        #[cfg(test)]
+        const ALL_CODES: &[KafkaErrorCode] = &[$(KafkaErrorCode::$variant,)*];
+    };
+}
+
+// Generated from the rd_kafka_resp_err_t constants in bindings.rs, local
+// (negative) codes first. The _BEGIN/_END/END_ALL markers are left out.
+error_codes! {
+    BadMsg => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__BAD_MSG,
+    BadCompression => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__BAD_COMPRESSION,
+    Destroy => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__DESTROY,
+    Fail => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__FAIL,
+    Transport => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__TRANSPORT,
+    CritSysResource => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__CRIT_SYS_RESOURCE,
+    Resolve => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__RESOLVE,
+    MsgTimedOut => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__MSG_TIMED_OUT,
+    PartitionEof => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__PARTITION_EOF,
+    UnknownPartition => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNKNOWN_PARTITION,
+    Fs => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__FS,
+    UnknownTopic => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNKNOWN_TOPIC,
+    AllBrokersDown => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__ALL_BROKERS_DOWN,
+    InvalidArg => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__INVALID_ARG,
+    TimedOut => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__TIMED_OUT,
+    QueueFull => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__QUEUE_FULL,
+    IsrInsuff => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__ISR_INSUFF,
+    NodeUpdate => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__NODE_UPDATE,
+    Ssl => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__SSL,
+    WaitCoord => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__WAIT_COORD,
+    UnknownGroup => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNKNOWN_GROUP,
+    InProgress => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__IN_PROGRESS,
+    PrevInProgress => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__PREV_IN_PROGRESS,
+    ExistingSubscription => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__EXISTING_SUBSCRIPTION,
+    AssignPartitions => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__ASSIGN_PARTITIONS,
+    RevokePartitions => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__REVOKE_PARTITIONS,
+    Conflict => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__CONFLICT,
+    State => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__STATE,
+    UnknownProtocol => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNKNOWN_PROTOCOL,
+    NotImplemented => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__NOT_IMPLEMENTED,
+    Authentication => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__AUTHENTICATION,
+    NoOffset => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__NO_OFFSET,
+    Outdated => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__OUTDATED,
+    TimedOutQueue => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__TIMED_OUT_QUEUE,
+    UnsupportedFeature => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNSUPPORTED_FEATURE,
+    WaitCache => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__WAIT_CACHE,
+    Intr => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__INTR,
+    KeySerialization => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__KEY_SERIALIZATION,
+    ValueSerialization => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__VALUE_SERIALIZATION,
+    KeyDeserialization => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__KEY_DESERIALIZATION,
+    ValueDeserialization => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__VALUE_DESERIALIZATION,
+    Partial => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__PARTIAL,
+    ReadOnly => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__READ_ONLY,
+    Noent => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__NOENT,
+    Underflow => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNDERFLOW,
+    InvalidType => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__INVALID_TYPE,
+    Retry => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__RETRY,
+    PurgeQueue => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__PURGE_QUEUE,
+    PurgeInflight => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__PURGE_INFLIGHT,
+    Fatal => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__FATAL,
+    Inconsistent => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__INCONSISTENT,
+    GaplessGuarantee => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__GAPLESS_GUARANTEE,
+    MaxPollExceeded => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__MAX_POLL_EXCEEDED,
+    UnknownBroker => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__UNKNOWN_BROKER,
+    NotConfigured => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__NOT_CONFIGURED,
+    Fenced => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__FENCED,
+    Application => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__APPLICATION,
+    Unknown => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNKNOWN,
+    NoError => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NO_ERROR,
+    OffsetOutOfRange => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_OFFSET_OUT_OF_RANGE,
+    InvalidMsg => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_MSG,
+    UnknownTopicOrPart => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNKNOWN_TOPIC_OR_PART,
+    InvalidMsgSize => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_MSG_SIZE,
+    LeaderNotAvailable => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_LEADER_NOT_AVAILABLE,
+    NotLeaderForPartition => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NOT_LEADER_FOR_PARTITION,
+    RequestTimedOut => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_REQUEST_TIMED_OUT,
+    BrokerNotAvailable => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_BROKER_NOT_AVAILABLE,
+    ReplicaNotAvailable => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_REPLICA_NOT_AVAILABLE,
+    MsgSizeTooLarge => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_MSG_SIZE_TOO_LARGE,
+    StaleCtrlEpoch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_STALE_CTRL_EPOCH,
+    OffsetMetadataTooLarge => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_OFFSET_METADATA_TOO_LARGE,
+    NetworkException => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NETWORK_EXCEPTION,
+    CoordinatorLoadInProgress => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_COORDINATOR_LOAD_IN_PROGRESS,
+    CoordinatorNotAvailable => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_COORDINATOR_NOT_AVAILABLE,
+    NotCoordinator => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NOT_COORDINATOR,
+    TopicException => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_TOPIC_EXCEPTION,
+    RecordListTooLarge => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_RECORD_LIST_TOO_LARGE,
+    NotEnoughReplicas => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NOT_ENOUGH_REPLICAS,
+    NotEnoughReplicasAfterAppend => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NOT_ENOUGH_REPLICAS_AFTER_APPEND,
+    InvalidRequiredAcks => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_REQUIRED_ACKS,
+    IllegalGeneration => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_ILLEGAL_GENERATION,
+    InconsistentGroupProtocol => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INCONSISTENT_GROUP_PROTOCOL,
+    InvalidGroupId => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_GROUP_ID,
+    UnknownMemberId => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNKNOWN_MEMBER_ID,
+    InvalidSessionTimeout => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_SESSION_TIMEOUT,
+    RebalanceInProgress => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_REBALANCE_IN_PROGRESS,
+    InvalidCommitOffsetSize => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_COMMIT_OFFSET_SIZE,
+    TopicAuthorizationFailed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_TOPIC_AUTHORIZATION_FAILED,
+    GroupAuthorizationFailed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_GROUP_AUTHORIZATION_FAILED,
+    ClusterAuthorizationFailed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_CLUSTER_AUTHORIZATION_FAILED,
+    InvalidTimestamp => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_TIMESTAMP,
+    UnsupportedSaslMechanism => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNSUPPORTED_SASL_MECHANISM,
+    IllegalSaslState => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_ILLEGAL_SASL_STATE,
+    UnsupportedVersion => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNSUPPORTED_VERSION,
+    TopicAlreadyExists => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_TOPIC_ALREADY_EXISTS,
+    InvalidPartitions => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_PARTITIONS,
+    InvalidReplicationFactor => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_REPLICATION_FACTOR,
+    InvalidReplicaAssignment => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_REPLICA_ASSIGNMENT,
+    InvalidConfig => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_CONFIG,
+    NotController => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NOT_CONTROLLER,
+    InvalidRequest => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_REQUEST,
+    UnsupportedForMessageFormat => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNSUPPORTED_FOR_MESSAGE_FORMAT,
+    PolicyViolation => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_POLICY_VIOLATION,
+    OutOfOrderSequenceNumber => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_OUT_OF_ORDER_SEQUENCE_NUMBER,
+    DuplicateSequenceNumber => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DUPLICATE_SEQUENCE_NUMBER,
+    InvalidProducerEpoch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_PRODUCER_EPOCH,
+    InvalidTxnState => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_TXN_STATE,
+    InvalidProducerIdMapping => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_PRODUCER_ID_MAPPING,
+    InvalidTransactionTimeout => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_TRANSACTION_TIMEOUT,
+    ConcurrentTransactions => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_CONCURRENT_TRANSACTIONS,
+    TransactionCoordinatorFenced => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_TRANSACTION_COORDINATOR_FENCED,
+    TransactionalIdAuthorizationFailed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_TRANSACTIONAL_ID_AUTHORIZATION_FAILED,
+    SecurityDisabled => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_SECURITY_DISABLED,
+    OperationNotAttempted => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_OPERATION_NOT_ATTEMPTED,
+    KafkaStorageError => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_KAFKA_STORAGE_ERROR,
+    LogDirNotFound => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_LOG_DIR_NOT_FOUND,
+    SaslAuthenticationFailed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_SASL_AUTHENTICATION_FAILED,
+    UnknownProducerId => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNKNOWN_PRODUCER_ID,
+    ReassignmentInProgress => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_REASSIGNMENT_IN_PROGRESS,
+    DelegationTokenAuthDisabled => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DELEGATION_TOKEN_AUTH_DISABLED,
+    DelegationTokenNotFound => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DELEGATION_TOKEN_NOT_FOUND,
+    DelegationTokenOwnerMismatch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DELEGATION_TOKEN_OWNER_MISMATCH,
+    DelegationTokenRequestNotAllowed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DELEGATION_TOKEN_REQUEST_NOT_ALLOWED,
+    DelegationTokenAuthorizationFailed => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DELEGATION_TOKEN_AUTHORIZATION_FAILED,
+    DelegationTokenExpired => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_DELEGATION_TOKEN_EXPIRED,
+    InvalidPrincipalType => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_PRINCIPAL_TYPE,
+    NonEmptyGroup => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_NON_EMPTY_GROUP,
+    GroupIdNotFound => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_GROUP_ID_NOT_FOUND,
+    FetchSessionIdNotFound => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_FETCH_SESSION_ID_NOT_FOUND,
+    InvalidFetchSessionEpoch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_INVALID_FETCH_SESSION_EPOCH,
+    ListenerNotFound => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_LISTENER_NOT_FOUND,
+    TopicDeletionDisabled => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_TOPIC_DELETION_DISABLED,
+    FencedLeaderEpoch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_FENCED_LEADER_EPOCH,
+    UnknownLeaderEpoch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNKNOWN_LEADER_EPOCH,
+    UnsupportedCompressionType => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_UNSUPPORTED_COMPRESSION_TYPE,
+    StaleBrokerEpoch => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_STALE_BROKER_EPOCH,
+    OffsetNotAvailable => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_OFFSET_NOT_AVAILABLE,
+    MemberIdRequired => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_MEMBER_ID_REQUIRED,
+    PreferredLeaderNotAvailable => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_PREFERRED_LEADER_NOT_AVAILABLE,
+    GroupMaxSizeReached => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_GROUP_MAX_SIZE_REACHED,
+    FencedInstanceId => rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR_FENCED_INSTANCE_ID,
+}
+
+impl KafkaErrorCode {
+    /// Symbolic name, e.g. `_PARTITION_EOF`
+    pub fn name(self) -> &'static str {
+        unsafe { static_str(rd_kafka_err2name(self.into())) }
+    }
+
+    /// Human readable description
+    pub fn description(self) -> &'static str {
+        unsafe { static_str(rd_kafka_err2str(self.into())) }
+    }
+}
+
+impl fmt::Display for KafkaErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+// err2name and err2str return pointers into a static table
+unsafe fn static_str(s: *const std::os::raw::c_char) -> &'static str {
+    CStr::from_ptr(s).to_str().unwrap_or("")
+}
+
+/// Error reported by librdkafka. Keeps the error code so callers can match
+/// on it, plus the detailed reason when librdkafka gave one.
+///
+/// The fatal, retriable and abort flags are only known for errors coming
+/// from an `rd_kafka_error_t` or from `rd_kafka_fatal_error`; errors built
+/// from a bare code have them unset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KafkaError {
+    code: KafkaErrorCode,
+    reason: Option<String>,
+    fatal: bool,
+    retriable: bool,
+    txn_requires_abort: bool,
+}
+
+impl KafkaError {
+    pub fn new(code: KafkaErrorCode) -> KafkaError {
+        KafkaError {
+            code,
+            reason: None,
+            fatal: false,
+            retriable: false,
+            txn_requires_abort: false,
+        }
+    }
+
+    pub fn with_reason(code: KafkaErrorCode, reason: &str) -> KafkaError {
+        KafkaError {
+            reason: Some(String::from(reason)),
+            ..KafkaError::new(code)
+        }
+    }
+
+    /// Takes ownership of `error` and destroys it.
+    ///
+    /// # Safety
+    ///
+    /// `error` must be a valid `rd_kafka_error_t` that is not used afterwards.
+    pub unsafe fn from_native(error: *mut rd_kafka_error_t) -> KafkaError {
+        let err = KafkaError {
+            code: rd_kafka_error_code(error).into(),
+            reason: Some(
+                CStr::from_ptr(rd_kafka_error_string(error))
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            fatal: rd_kafka_error_is_fatal(error) != 0,
+            retriable: rd_kafka_error_is_retriable(error) != 0,
+            txn_requires_abort: rd_kafka_error_txn_requires_abort(error) != 0,
+        };
+        rd_kafka_error_destroy(error);
+        err
+    }
+
+    /// The fatal error raised on `rk`, if any. Once set, the client instance
+    /// is unusable and has to be recreated.
+    pub(crate) unsafe fn fatal(rk: *mut rd_kafka_t) -> Option<KafkaError> {
+        let mut buf = [0 as c_char; 512];
+        let code = rd_kafka_fatal_error(rk, buf.as_mut_ptr(), buf.len() as u64);
+        if code == 0 {
+            return None;
+        }
+
+        Some(KafkaError {
+            reason: Some(CStr::from_ptr(buf.as_ptr()).to_string_lossy().to_string()),
+            fatal: true,
+            ..KafkaError::new(code.into())
+        })
+    }
+
+    /// Replaces a generic `_FATAL` error with the underlying fatal error
+    pub(crate) unsafe fn resolve_fatal(self, rk: *mut rd_kafka_t) -> KafkaError {
+        if self.code != KafkaErrorCode::Fatal {
+            return self;
+        }
+
+        match KafkaError::fatal(rk) {
+            Some(err) => err,
+            None => KafkaError {
+                fatal: true,
+                ..self
+            },
+        }
+    }
+
+    pub fn code(&self) -> KafkaErrorCode {
+        self.code
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        self.fatal
+    }
+
+    pub fn is_retriable(&self) -> bool {
+        self.retriable
+    }
+
+    /// Whether the current transaction must be aborted
+    pub fn txn_requires_abort(&self) -> bool {
+        self.txn_requires_abort
+    }
+
+    pub fn reason(&self) -> &str {
+        match &self.reason {
+            Some(r) => r,
+            None => self.code.description(),
+        }
+    }
+}
+
+impl From<KafkaErrorCode> for KafkaError {
+    fn from(code: KafkaErrorCode) -> KafkaError {
+        KafkaError::new(code)
+    }
+}
+
+impl fmt::Display for KafkaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.reason(), self.code.name())
+    }
+}
+
+impl std::error::Error for KafkaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_code_round_trips() {
+        for code in ALL_CODES {
+            let raw = rd_kafka_resp_err_t::from(*code);
+            assert_eq!(KafkaErrorCode::from(raw), *code);
+        }
+    }
+
+    #[test]
+    fn unknown_code_is_kept_in_other() {
+        assert_eq!(KafkaErrorCode::from(12345), KafkaErrorCode::Other(12345));
+        assert_eq!(rd_kafka_resp_err_t::from(KafkaErrorCode::Other(12345)), 12345);
+    }
+
+    #[test]
+    fn name_is_the_symbolic_name() {
+        assert_eq!(KafkaErrorCode::PartitionEof.name(), "_PARTITION_EOF");
+        assert_eq!(KafkaErrorCode::QueueFull.name(), "_QUEUE_FULL");
+    }
+}
@@ -0,0 +1,121 @@
+use super::error::{KafkaError, KafkaErrorCode};
+use super::producer::{Producer, ProducerError};
+use super::record::OwnedRecord;
+use super::threaded_producer::ThreadedProducer;
+
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Tracks polls that served events, i.e. points at which queue space may
+/// have been freed by delivery reports.
+#[derive(Default)]
+struct QueueSpace {
+    inner: Mutex<SpaceInner>,
+}
+
+#[derive(Default)]
+struct SpaceInner {
+    generation: u64,
+    wakers: Vec<Waker>,
+}
+
+impl QueueSpace {
+    fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    fn notify(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        for waker in inner.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves once events were served after `generation` was read
+struct WaitForSpace<'a> {
+    space: &'a QueueSpace,
+    generation: u64,
+}
+
+impl<'a> Future for WaitForSpace<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.space.inner.lock().unwrap();
+        if inner.generation != self.generation {
+            return Poll::Ready(());
+        }
+        inner.wakers.push(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Producer whose sends resolve once the broker acknowledged the message.
+///
+/// Delivery reports are served by a background poll thread. When the local
+/// queue is full, sending waits for space instead of failing.
+pub struct FutureProducer {
+    producer: ThreadedProducer,
+    space: Arc<QueueSpace>,
+}
+
+impl FutureProducer {
+    pub fn new(producer: Producer) -> FutureProducer {
+        let space = Arc::new(QueueSpace::default());
+        let poll_space = space.clone();
+        let producer = ThreadedProducer::with_poll_callback(producer, move |events| {
+            if events > 0 {
+                poll_space.notify();
+            }
+        });
+        FutureProducer { producer, space }
+    }
+
+    /// How long dropping the producer may block flushing outstanding messages
+    pub fn set_flush_timeout(&mut self, timeout_ms: i32) -> &mut Self {
+        self.producer.set_flush_timeout(timeout_ms);
+        self
+    }
+
+    /// Produces `record` and resolves to the partition and offset it was
+    /// written to. On failure the record is handed back along with the error.
+    pub async fn send(&self, record: OwnedRecord) -> Result<(i32, i64), (KafkaError, OwnedRecord)> {
+        loop {
+            let generation = self.space.generation();
+            match self.producer.try_send_record(&record.as_record()) {
+                Ok(token) => {
+                    return token.await.map_err(|err| (into_kafka_error(err), record));
+                }
+                Err(ProducerError::SendError(ref err))
+                    if err.code() == KafkaErrorCode::QueueFull =>
+                {
+                    WaitForSpace {
+                        space: &self.space,
+                        generation,
+                    }
+                    .await
+                }
+                Err(err) => return Err((into_kafka_error(err), record)),
+            }
+        }
+    }
+}
+
+impl Deref for FutureProducer {
+    type Target = Producer;
+
+    fn deref(&self) -> &Producer {
+        &self.producer
+    }
+}
+
+fn into_kafka_error(err: ProducerError) -> KafkaError {
+    match err {
+        ProducerError::SendError(err) | ProducerError::DeliveryError(err) => err,
+    }
+}
@@ -0,0 +1,193 @@
+use crate::bindings::{
+    rd_kafka_header_add, rd_kafka_header_get_all, rd_kafka_headers_new, rd_kafka_headers_t,
+};
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use std::ptr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub name: String,
+    pub value: Option<Vec<u8>>,
+}
+
+/// Ordered list of record headers. A name may appear more than once and
+/// insertion order is kept, as Kafka does on the wire.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Headers {
+    headers: Vec<Header>,
+}
+
+impl Headers {
+    pub fn new() -> Headers {
+        Headers {
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, value: Option<&[u8]>) -> &mut Self {
+        self.headers.push(Header {
+            name: String::from(name),
+            value: value.map(|v| v.to_vec()),
+        });
+        self
+    }
+
+    /// Removes every header called `name`
+    pub fn remove(&mut self, name: &str) -> &mut Self {
+        self.headers.retain(|h| h.name != name);
+        self
+    }
+
+    /// Returns the last header called `name`
+    pub fn get_last(&self, name: &str) -> Option<&Header> {
+        self.headers.iter().rev().find(|h| h.name == name)
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Header> {
+        self.headers.iter().filter(move |h| h.name == name)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Header> {
+        self.headers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// Builds a native header list. The caller owns the result until it is
+    /// handed over to librdkafka.
+    pub(crate) fn to_native(&self) -> *mut rd_kafka_headers_t {
+        unsafe {
+            let hdrs = rd_kafka_headers_new(self.headers.len() as u64);
+            for h in &self.headers {
+                let (val, val_len) = match &h.value {
+                    Some(v) => (v.as_ptr() as *const c_void, v.len() as i64),
+                    None => (ptr::null(), 0),
+                };
+                rd_kafka_header_add(
+                    hdrs,
+                    h.name.as_ptr() as *const c_char,
+                    h.name.len() as i64,
+                    val,
+                    val_len,
+                );
+            }
+            hdrs
+        }
+    }
+
+    pub(crate) fn from_native(hdrs: *const rd_kafka_headers_t) -> Headers {
+        let mut headers = Headers::new();
+        if hdrs.is_null() {
+            return headers;
+        }
+
+        unsafe {
+            let mut idx = 0;
+            loop {
+                let mut name: *const c_char = ptr::null();
+                let mut val: *const c_void = ptr::null();
+                let mut val_len = 0;
+                if rd_kafka_header_get_all(hdrs, idx, &mut name, &mut val, &mut val_len) != 0 {
+                    break;
+                }
+
+                let name = CStr::from_ptr(name).to_string_lossy();
+                let value = match val.is_null() {
+                    true => None,
+                    false => Some(std::slice::from_raw_parts(val as *const u8, val_len as usize)),
+                };
+                headers.add(&name, value);
+                idx += 1;
+            }
+        }
+        headers
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a Header;
+    type IntoIter = std::slice::Iter<'a, Header>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.headers.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values<'a>(headers: impl Iterator<Item = &'a Header>) -> Vec<Option<&'a [u8]>> {
+        headers.map(|h| h.value.as_deref()).collect()
+    }
+
+    #[test]
+    fn duplicate_names_keep_insertion_order() {
+        let mut headers = Headers::new();
+        headers
+            .add("a", Some(b"1"))
+            .add("b", Some(b"2"))
+            .add("a", Some(b"3"));
+
+        let names: Vec<&str> = headers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "a"]);
+        assert_eq!(
+            values(headers.iter()),
+            [Some(&b"1"[..]), Some(&b"2"[..]), Some(&b"3"[..])]
+        );
+    }
+
+    #[test]
+    fn get_last_and_get_all() {
+        let mut headers = Headers::new();
+        headers
+            .add("a", Some(b"1"))
+            .add("b", Some(b"2"))
+            .add("a", Some(b"3"));
+
+        assert_eq!(
+            headers.get_last("a").unwrap().value.as_deref(),
+            Some(&b"3"[..])
+        );
+        assert_eq!(
+            values(headers.get_all("a")),
+            [Some(&b"1"[..]), Some(&b"3"[..])]
+        );
+        assert!(headers.get_last("c").is_none());
+        assert_eq!(headers.get_all("c").count(), 0);
+    }
+
+    #[test]
+    fn remove_drops_every_header_with_the_name() {
+        let mut headers = Headers::new();
+        headers
+            .add("a", Some(b"1"))
+            .add("b", Some(b"2"))
+            .add("a", Some(b"3"));
+        headers.remove("a");
+
+        assert_eq!(headers.len(), 1);
+        assert!(headers.get_last("a").is_none());
+        assert_eq!(
+            headers.get_last("b").unwrap().value.as_deref(),
+            Some(&b"2"[..])
+        );
+    }
+
+    #[test]
+    fn null_value_is_distinct_from_empty_value() {
+        let mut headers = Headers::new();
+        headers.add("null", None).add("empty", Some(b""));
+
+        assert_eq!(headers.get_last("null").unwrap().value, None);
+        assert_eq!(headers.get_last("empty").unwrap().value, Some(Vec::new()));
+        assert_ne!(headers.get_last("null"), headers.get_last("empty"));
+    }
+}
@@ -1,30 +1,268 @@
+use super::error::KafkaError;
+use super::headers::Headers;
 use crate::bindings::{
-    rd_kafka_consumer_poll, rd_kafka_message_destroy, rd_kafka_message_t, rd_kafka_s,
+    self, rd_kafka_consumer_poll, rd_kafka_headers_t, rd_kafka_message_broker_id,
+    rd_kafka_message_destroy, rd_kafka_message_errstr, rd_kafka_message_headers,
+    rd_kafka_message_latency, rd_kafka_message_t, rd_kafka_message_timestamp, rd_kafka_s,
+    rd_kafka_topic_name,
 };
+use std::ffi::CStr;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timestamp {
+    NotAvailable,
+    /// Set by the producer, in milliseconds since the epoch
+    CreateTime(i64),
+    /// Set by the broker when appending to the log, in milliseconds since the epoch
+    LogAppendTime(i64),
+}
+
+impl Timestamp {
+    pub fn to_millis(&self) -> Option<i64> {
+        match self {
+            Timestamp::NotAvailable => None,
+            Timestamp::CreateTime(ts) | Timestamp::LogAppendTime(ts) => Some(*ts),
+        }
+    }
+
+    unsafe fn from_kafka_msg(msg: *const rd_kafka_message_t) -> Timestamp {
+        let mut ts_type = 0;
+        let ts = rd_kafka_message_timestamp(msg, &mut ts_type);
+        match ts_type {
+            bindings::rd_kafka_timestamp_type_t_RD_KAFKA_TIMESTAMP_CREATE_TIME => {
+                Timestamp::CreateTime(ts)
+            }
+            bindings::rd_kafka_timestamp_type_t_RD_KAFKA_TIMESTAMP_LOG_APPEND_TIME => {
+                Timestamp::LogAppendTime(ts)
+            }
+            _ => Timestamp::NotAvailable,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Message {
+    pub topic: String,
     pub payload: Vec<u8>,
+    pub key: Option<Vec<u8>>,
+    pub headers: Headers,
     pub partition: i32,
     pub offset: i64,
+    pub timestamp: Timestamp,
+    /// Time from produce call to delivery, only known for produced messages
+    pub latency: Option<Duration>,
+    /// Broker the message was fetched from, if known
+    pub broker_id: Option<i32>,
 }
 
 impl Message {
     pub fn read(&self) -> Result<&str, std::str::Utf8Error> {
         std::str::from_utf8(&self.payload)
     }
+}
+
+/// Message still owned by librdkafka. Payload and key are read in place
+/// without copying, and the native message is destroyed on drop.
+///
+/// The message cannot outlive the consumer it was polled from; use `detach`
+/// to get an owned `Message`.
+pub struct BorrowedMessage<'c> {
+    ptr: *mut rd_kafka_message_t,
+    _consumer: PhantomData<&'c ()>,
+}
+
+impl<'c> BorrowedMessage<'c> {
+    /// Takes ownership of a native message
+    unsafe fn from_ptr(ptr: *mut rd_kafka_message_t) -> BorrowedMessage<'c> {
+        BorrowedMessage {
+            ptr,
+            _consumer: PhantomData,
+        }
+    }
+
+    fn msg(&self) -> &rd_kafka_message_t {
+        unsafe { &*self.ptr }
+    }
 
-    fn from_kafka_msg(msg: *mut rd_kafka_message_t) -> Self {
+    pub fn topic(&self) -> &str {
         unsafe {
-            let payload_len = (*msg).len as usize;
-            let b = std::slice::from_raw_parts((*msg).payload as *const u8, payload_len);
-            Self {
-                payload: b.to_vec(),
-                partition: (*msg).partition,
-                offset: (*msg).offset,
+            match self.msg().rkt.is_null() {
+                true => "",
+                false => CStr::from_ptr(rd_kafka_topic_name(self.msg().rkt))
+                    .to_str()
+                    .unwrap_or(""),
             }
         }
     }
+
+    /// Message payload, empty for a null (tombstone) payload
+    pub fn payload(&self) -> &[u8] {
+        let msg = self.msg();
+        match msg.payload.is_null() {
+            true => &[],
+            false => unsafe {
+                std::slice::from_raw_parts(msg.payload as *const u8, msg.len as usize)
+            },
+        }
+    }
+
+    pub fn key(&self) -> Option<&[u8]> {
+        let msg = self.msg();
+        match msg.key.is_null() {
+            true => None,
+            false => unsafe {
+                Some(std::slice::from_raw_parts(
+                    msg.key as *const u8,
+                    msg.key_len as usize,
+                ))
+            },
+        }
+    }
+
+    pub fn read(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(self.payload())
+    }
+
+    /// Copies the message headers
+    pub fn headers(&self) -> Headers {
+        unsafe {
+            let mut hdrs: *mut rd_kafka_headers_t = std::ptr::null_mut();
+            match rd_kafka_message_headers(self.ptr, &mut hdrs) {
+                0 => Headers::from_native(hdrs),
+                _ => Headers::new(),
+            }
+        }
+    }
+
+    pub fn partition(&self) -> i32 {
+        self.msg().partition
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.msg().offset
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        unsafe { Timestamp::from_kafka_msg(self.ptr) }
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        match unsafe { rd_kafka_message_latency(self.ptr) } {
+            -1 => None,
+            us => Some(Duration::from_micros(us as u64)),
+        }
+    }
+
+    pub fn broker_id(&self) -> Option<i32> {
+        match unsafe { rd_kafka_message_broker_id(self.ptr) } {
+            -1 => None,
+            id => Some(id),
+        }
+    }
+
+    /// Copies the message into an owned `Message`
+    pub fn detach(&self) -> Message {
+        Message {
+            topic: String::from(self.topic()),
+            payload: self.payload().to_vec(),
+            key: self.key().map(|k| k.to_vec()),
+            headers: self.headers(),
+            partition: self.partition(),
+            offset: self.offset(),
+            timestamp: self.timestamp(),
+            latency: self.latency(),
+            broker_id: self.broker_id(),
+        }
+    }
+}
+
+impl<'c> Drop for BorrowedMessage<'c> {
+    fn drop(&mut self) {
+        unsafe { rd_kafka_message_destroy(self.ptr) }
+    }
+}
+
+impl<'c> fmt::Debug for BorrowedMessage<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BorrowedMessage")
+            .field("topic", &self.topic())
+            .field("partition", &self.partition())
+            .field("offset", &self.offset())
+            .finish()
+    }
+}
+
+unsafe impl<'c> Send for BorrowedMessage<'c> {}
+
+/// Where a consumed message sits in its partition, implemented by both owned
+/// and borrowed messages
+pub trait MessagePosition {
+    fn topic(&self) -> &str;
+    fn partition(&self) -> i32;
+    fn offset(&self) -> i64;
+}
+
+impl MessagePosition for Message {
+    fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    fn partition(&self) -> i32 {
+        self.partition
+    }
+
+    fn offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+impl<'c> MessagePosition for BorrowedMessage<'c> {
+    fn topic(&self) -> &str {
+        BorrowedMessage::topic(self)
+    }
+
+    fn partition(&self) -> i32 {
+        BorrowedMessage::partition(self)
+    }
+
+    fn offset(&self) -> i64 {
+        BorrowedMessage::offset(self)
+    }
+}
+unsafe impl<'c> Sync for BorrowedMessage<'c> {}
+
+/// Polls the consumer queue of `rk` once. Error events are destroyed and
+/// returned as errors. The caller picks a lifetime no longer than the
+/// consumer's.
+pub(crate) unsafe fn poll_message<'c>(
+    rk: *mut rd_kafka_s,
+    timeout_ms: i32,
+) -> Option<Result<BorrowedMessage<'c>, MessageError>> {
+    let msg = rd_kafka_consumer_poll(rk, timeout_ms);
+    if msg.is_null() {
+        return None;
+    }
+    Some(take_message(msg))
+}
+
+/// Takes ownership of a non-null message returned by librdkafka
+pub(crate) unsafe fn take_message<'c>(
+    msg: *mut rd_kafka_message_t,
+) -> Result<BorrowedMessage<'c>, MessageError> {
+    match super::get_error((*msg).err) {
+        Some(err) => {
+            let reason = CStr::from_ptr(rd_kafka_message_errstr(msg));
+            let err = KafkaError::with_reason(err.code(), &reason.to_string_lossy());
+            rd_kafka_message_destroy(msg);
+            Err(MessageError::KafkaError(err))
+        }
+        None => Ok(BorrowedMessage::from_ptr(msg)),
+    }
 }
 
 pub struct Messages {
@@ -46,26 +284,100 @@ impl Iterator for Messages {
 
     fn next(&mut self) -> Option<Self::Item> {
         unsafe {
-            let msg = rd_kafka_consumer_poll(self.rk, self.poll_timeout_ms);
-            match msg.is_null() {
-                true => None,
-                false => {
-                    let res = match super::get_error_str((*msg).err) {
-                        Some(s) => Some(Err(MessageError::KafkaError(s))),
-                        None => Some(Ok(Message::from_kafka_msg(msg))),
-                    };
-                    rd_kafka_message_destroy(msg);
-                    res
+            poll_message(self.rk, self.poll_timeout_ms).map(|res| res.map(|msg| msg.detach()))
+        }
+    }
+}
+
+/// Stops a `MessageStream`, possibly from another thread
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle {
+    shutdown: Arc<AtomicBool>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> ShutdownHandle {
+        ShutdownHandle::default()
+    }
+
+    /// Makes the stream end after its current poll, within
+    /// `STREAM_POLL_INTERVAL_MS`
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConsumerEvent {
+    Message(Message),
+    /// The consumer reached the end of a partition; `offset` is the next
+    /// offset to be produced. Requires `enable.partition.eof=true`.
+    PartitionEof {
+        topic: String,
+        partition: i32,
+        offset: i64,
+    },
+}
+
+pub const STREAM_POLL_INTERVAL_MS: i32 = 100;
+
+/// Endless message iterator. Unlike `Messages` it keeps polling when no
+/// message arrives, and only ends once its `ShutdownHandle` is triggered.
+pub struct MessageStream<'c> {
+    rk: *mut rd_kafka_s,
+    shutdown: ShutdownHandle,
+    _consumer: PhantomData<&'c ()>,
+}
+
+impl<'c> MessageStream<'c> {
+    pub(crate) fn new(rk: *mut rd_kafka_s, shutdown: ShutdownHandle) -> MessageStream<'c> {
+        MessageStream {
+            rk,
+            shutdown,
+            _consumer: PhantomData,
+        }
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+}
+
+impl<'c> Iterator for MessageStream<'c> {
+    type Item = Result<ConsumerEvent, MessageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.shutdown.is_shutdown() {
+            unsafe {
+                let msg = rd_kafka_consumer_poll(self.rk, STREAM_POLL_INTERVAL_MS);
+                if msg.is_null() {
+                    continue;
                 }
+
+                if (*msg).err == bindings::rd_kafka_resp_err_t_RD_KAFKA_RESP_ERR__PARTITION_EOF {
+                    let eof = BorrowedMessage::from_ptr(msg);
+                    return Some(Ok(ConsumerEvent::PartitionEof {
+                        topic: String::from(eof.topic()),
+                        partition: eof.partition(),
+                        offset: eof.offset(),
+                    }));
+                }
+                return Some(take_message(msg).map(|msg| ConsumerEvent::Message(msg.detach())));
             }
         }
+        None
     }
 }
 
-use std::fmt;
+unsafe impl<'c> Send for MessageStream<'c> {}
+
 #[derive(Debug, Clone)]
 pub enum MessageError {
-    KafkaError(String),
+    KafkaError(KafkaError),
 }
 
 impl fmt::Display for MessageError {
@@ -76,6 +388,8 @@ impl fmt::Display for MessageError {
 
 impl std::error::Error for MessageError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        match self {
+            MessageError::KafkaError(err) => Some(err),
+        }
     }
 }
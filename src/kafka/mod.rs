@@ -1,24 +1,53 @@
 pub mod config;
 pub mod consumer;
-pub mod producer;
+pub mod consumer_context;
+pub mod delivery;
+pub mod error;
+pub mod future_producer;
+pub mod headers;
 pub mod message;
+pub mod offset;
+pub mod producer;
+pub mod producer_context;
+pub mod record;
+#[cfg(feature = "async")]
+pub mod stream_consumer;
+pub mod threaded_producer;
+pub mod topic_partition_list;
 
-pub use consumer::Consumer;
+pub use consumer::{CommitMode, Consumer};
+pub use consumer_context::{ConsumerContext, DefaultConsumerContext, Rebalance};
+pub use delivery::DeliveryToken;
+pub use error::{KafkaError, KafkaErrorCode};
+pub use future_producer::FutureProducer;
+pub use headers::Headers;
+pub use message::{
+    BorrowedMessage, ConsumerEvent, Message, MessagePosition, ShutdownHandle, Timestamp,
+};
+pub use offset::Offset;
 pub use producer::Producer;
+pub use producer_context::{DefaultProducerContext, ProducerContext};
+pub use record::{OwnedRecord, Record};
+#[cfg(feature = "async")]
+pub use stream_consumer::StreamConsumer;
+pub use threaded_producer::ThreadedProducer;
+pub use topic_partition_list::TopicPartitionList;
 
-use crate::bindings::{rd_kafka_resp_err_t, rd_kafka_err2str};
-use std::ffi::CStr;
+use crate::bindings::rd_kafka_resp_err_t;
+use std::panic::{self, AssertUnwindSafe};
 
-pub fn get_error_str(code: rd_kafka_resp_err_t) -> Option<String> {
+pub fn get_error(code: rd_kafka_resp_err_t) -> Option<KafkaError> {
     if code == 0 {
         return None;
     }
 
-    unsafe {
-        Some(
-            CStr::from_ptr(rd_kafka_err2str(code))
-                .to_string_lossy()
-                .to_string(),
-        )
+    Some(KafkaError::new(code.into()))
+}
+
+/// Runs a context hook from a librdkafka callback. Unwinding into C is
+/// undefined behavior, so a panic aborts instead.
+pub(crate) fn call_hook<F: FnOnce()>(hook: F) {
+    if panic::catch_unwind(AssertUnwindSafe(hook)).is_err() {
+        std::process::abort();
     }
 }
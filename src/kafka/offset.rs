@@ -0,0 +1,92 @@
+use crate::bindings::{
+    RD_KAFKA_OFFSET_BEGINNING, RD_KAFKA_OFFSET_END, RD_KAFKA_OFFSET_INVALID,
+    RD_KAFKA_OFFSET_STORED, RD_KAFKA_OFFSET_TAIL_BASE,
+};
+
+/// Partition offset, including librdkafka's logical offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Offset {
+    /// Oldest message still in the partition
+    Beginning,
+    /// Only messages produced from now on
+    End,
+    /// Offset committed by the consumer group
+    Stored,
+    Invalid,
+    /// A concrete offset
+    Offset(i64),
+    /// `n` messages before the end of the partition
+    Tail(i64),
+}
+
+impl Offset {
+    pub fn from_raw(raw: i64) -> Offset {
+        match raw {
+            r if r >= 0 => Offset::Offset(r),
+            r if r == RD_KAFKA_OFFSET_BEGINNING as i64 => Offset::Beginning,
+            r if r == RD_KAFKA_OFFSET_END as i64 => Offset::End,
+            r if r == RD_KAFKA_OFFSET_STORED as i64 => Offset::Stored,
+            r if r <= RD_KAFKA_OFFSET_TAIL_BASE as i64 => {
+                Offset::Tail(RD_KAFKA_OFFSET_TAIL_BASE as i64 - r)
+            }
+            _ => Offset::Invalid,
+        }
+    }
+
+    pub fn to_raw(self) -> i64 {
+        match self {
+            Offset::Beginning => RD_KAFKA_OFFSET_BEGINNING as i64,
+            Offset::End => RD_KAFKA_OFFSET_END as i64,
+            Offset::Stored => RD_KAFKA_OFFSET_STORED as i64,
+            Offset::Invalid => RD_KAFKA_OFFSET_INVALID as i64,
+            Offset::Offset(o) => o,
+            Offset::Tail(n) => RD_KAFKA_OFFSET_TAIL_BASE as i64 - n,
+        }
+    }
+}
+
+impl From<i64> for Offset {
+    fn from(raw: i64) -> Offset {
+        Offset::from_raw(raw)
+    }
+}
+
+impl From<Offset> for i64 {
+    fn from(offset: Offset) -> i64 {
+        offset.to_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_round_trip() {
+        let offsets = [
+            Offset::Beginning,
+            Offset::End,
+            Offset::Stored,
+            Offset::Invalid,
+            Offset::Tail(0),
+            Offset::Tail(5),
+            Offset::Offset(0),
+            Offset::Offset(42),
+        ];
+        for offset in offsets.iter() {
+            assert_eq!(Offset::from_raw(offset.to_raw()), *offset);
+        }
+    }
+
+    #[test]
+    fn tail_is_relative_to_tail_base() {
+        assert_eq!(
+            Offset::Tail(5).to_raw(),
+            RD_KAFKA_OFFSET_TAIL_BASE as i64 - 5
+        );
+        assert_eq!(
+            Offset::from_raw(RD_KAFKA_OFFSET_TAIL_BASE as i64 - 5),
+            Offset::Tail(5)
+        );
+    }
+}
@@ -1,77 +1,366 @@
+use super::delivery::DeliveryToken;
+use super::error::{KafkaError, KafkaErrorCode};
+use super::headers::Headers;
+use super::producer_context::{DefaultProducerContext, ProducerContext};
+use super::record::Record;
 use crate::bindings::{
-    rd_kafka_destroy, rd_kafka_flush, rd_kafka_last_error, rd_kafka_poll, rd_kafka_produce,
-    rd_kafka_s, rd_kafka_topic_conf_new, rd_kafka_topic_destroy, rd_kafka_topic_new,
-    rd_kafka_topic_t, RD_KAFKA_MSG_F_FREE,
+    rd_kafka_destroy, rd_kafka_error_code, rd_kafka_error_destroy, rd_kafka_flush,
+    rd_kafka_headers_destroy, rd_kafka_last_error, rd_kafka_outq_len, rd_kafka_poll,
+    rd_kafka_produceva, rd_kafka_purge, rd_kafka_s, rd_kafka_topic_conf_new,
+    rd_kafka_topic_destroy, rd_kafka_topic_new, rd_kafka_topic_t, rd_kafka_vtype_t,
+    rd_kafka_vtype_t_RD_KAFKA_VTYPE_HEADERS, rd_kafka_vtype_t_RD_KAFKA_VTYPE_KEY,
+    rd_kafka_vtype_t_RD_KAFKA_VTYPE_MSGFLAGS, rd_kafka_vtype_t_RD_KAFKA_VTYPE_OPAQUE,
+    rd_kafka_vtype_t_RD_KAFKA_VTYPE_PARTITION, rd_kafka_vtype_t_RD_KAFKA_VTYPE_RKT,
+    rd_kafka_vtype_t_RD_KAFKA_VTYPE_TIMESTAMP, rd_kafka_vtype_t_RD_KAFKA_VTYPE_VALUE,
+    rd_kafka_vu_s__bindgen_ty_1 as VuValue, rd_kafka_vu_s__bindgen_ty_1__bindgen_ty_1 as VuMem,
+    rd_kafka_vu_t, RD_KAFKA_MSG_F_COPY, RD_KAFKA_PURGE_F_INFLIGHT, RD_KAFKA_PURGE_F_QUEUE,
 };
 use std::ffi::{c_void, CString};
+use std::os::raw::c_int;
 use std::ptr;
 use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+const QUEUE_FULL_POLL_MS: i32 = 100;
+
+fn vu(vtype: rd_kafka_vtype_t, u: VuValue) -> rd_kafka_vu_t {
+    rd_kafka_vu_t { vtype, u }
+}
 
-#[derive(Debug, Clone)]
 pub struct Producer {
     rk: *mut rd_kafka_s,
-    rkt: HashMap<String, *mut rd_kafka_topic_t>,
+    // handles are created on first use; a read lock is held while producing
+    // so a handle cannot be destroyed under a concurrent send
+    rkt: RwLock<HashMap<String, *mut rd_kafka_topic_t>>,
+    queue_full_timeout_ms: i32,
+    _context: Box<Box<dyn ProducerContext>>,
 }
 
 impl Producer {
     pub fn new(rk: *mut rd_kafka_s) -> Producer {
+        Producer::with_context(rk, Box::new(Box::new(DefaultProducerContext)))
+    }
+
+    pub(crate) fn with_context(
+        rk: *mut rd_kafka_s,
+        context: Box<Box<dyn ProducerContext>>,
+    ) -> Producer {
         Producer {
             rk,
-            rkt: HashMap::new(),
+            rkt: RwLock::new(HashMap::new()),
+            queue_full_timeout_ms: 0,
+            _context: context,
         }
     }
 
-    pub fn set_topics(&mut self, topics: &[&str]) {
-        unsafe {
-            for topic_name in topics {
-                if self.rkt.contains_key(*topic_name) {
-                    continue;
-                }
+    /// Makes sends wait up to `timeout_ms` for space when the local queue is
+    /// full, polling the producer meanwhile, before failing with `QueueFull`.
+    /// The default of 0 fails right away.
+    pub fn set_queue_full_timeout(&mut self, timeout_ms: i32) {
+        self.queue_full_timeout_ms = timeout_ms;
+    }
 
-                let topic = CString::new(String::from(*topic_name)).unwrap().into_raw();
-                let topic_conf = rd_kafka_topic_conf_new();
-                self.rkt.insert(
-                    topic_name.to_string(),
-                    rd_kafka_topic_new(self.rk, topic, topic_conf),
-                );
+    /// Creates the handles of `topics` ahead of the first send. Optional,
+    /// handles are otherwise created on demand; a topic whose handle cannot
+    /// be created reports the error on send.
+    pub fn set_topics(&self, topics: &[&str]) {
+        for topic in topics {
+            let _ = self.with_topic(topic, |_| ());
+        }
+    }
+
+    /// Drops the cached handle of `topic`, returning whether it was cached.
+    /// Messages already enqueued are not affected, and the handle is created
+    /// again on the next send to the topic.
+    pub fn remove_topic(&self, topic: &str) -> bool {
+        match self.rkt.write().unwrap().remove(topic) {
+            Some(rkt) => {
+                unsafe { rd_kafka_topic_destroy(rkt) };
+                true
             }
+            None => false,
         }
     }
 
-    pub fn send(&self, payload: &[u8], topic: &str, partition: Option<i32>) -> Result<(), ProducerError> {
-        let rkt = match self.rkt.get(topic) {
-            None => return Err(ProducerError::UnregisteredTopic),
-            Some(v) => v,
+    /// Runs `f` with the handle of `topic`, creating it if needed
+    fn with_topic<T, F>(&self, topic: &str, f: F) -> Result<T, KafkaError>
+    where
+        F: FnOnce(*mut rd_kafka_topic_t) -> T,
+    {
+        if let Some(rkt) = self.rkt.read().unwrap().get(topic) {
+            return Ok(f(*rkt));
+        }
+
+        let mut topics = self.rkt.write().unwrap();
+        let rkt = match topics.get(topic) {
+            Some(rkt) => *rkt,
+            None => unsafe {
+                let name = match CString::new(topic) {
+                    Ok(name) => name,
+                    Err(_) => return Err(KafkaError::new(KafkaErrorCode::InvalidArg)),
+                };
+                let rkt = rd_kafka_topic_new(self.rk, name.as_ptr(), rd_kafka_topic_conf_new());
+                if rkt.is_null() {
+                    return Err(KafkaError::new(rd_kafka_last_error().into()));
+                }
+                topics.insert(topic.to_string(), rkt);
+                rkt
+            },
         };
+        Ok(f(rkt))
+    }
 
-        unsafe {
-            let mut payload = payload.to_vec();
-            let res = rd_kafka_produce(
-                *rkt,
-                partition.unwrap_or(-1),
-                RD_KAFKA_MSG_F_FREE as i32,
-                payload.as_mut_ptr() as *mut c_void,
-                payload.len() as u64,
-                ptr::null(),
-                0,
-                ptr::null_mut(),
+    /// Enqueues the message and returns a token resolving to its delivery
+    /// report, which is only served while the producer is polled.
+    pub fn send(
+        &self,
+        payload: &[u8],
+        topic: &str,
+        partition: Option<i32>,
+    ) -> Result<DeliveryToken, ProducerError> {
+        self.send_record(&Record {
+            payload: Some(payload),
+            partition,
+            ..Record::new(topic)
+        })
+    }
+
+    /// Same as `send`, but attaches `key` to the message. With the default
+    /// partitioner, messages sharing a key always land on the same partition.
+    pub fn send_with_key(
+        &self,
+        payload: &[u8],
+        key: &[u8],
+        topic: &str,
+        partition: Option<i32>,
+    ) -> Result<DeliveryToken, ProducerError> {
+        self.send_record(&Record {
+            payload: Some(payload),
+            key: Some(key),
+            partition,
+            ..Record::new(topic)
+        })
+    }
+
+    /// Same as `send_with_key`, but also attaches `headers` to the message
+    pub fn send_with_headers(
+        &self,
+        payload: &[u8],
+        key: Option<&[u8]>,
+        headers: &Headers,
+        topic: &str,
+        partition: Option<i32>,
+    ) -> Result<DeliveryToken, ProducerError> {
+        self.send_record(&Record {
+            payload: Some(payload),
+            key,
+            headers: Some(headers),
+            partition,
+            ..Record::new(topic)
+        })
+    }
+
+    /// Enqueues the record and returns a token resolving to its delivery
+    /// report. Payload, key and headers are copied, so the record only
+    /// needs to live for the duration of the call.
+    pub fn send_record(&self, record: &Record<'_>) -> Result<DeliveryToken, ProducerError> {
+        self.send_copied(record, self.queue_full_timeout_ms)
+    }
+
+    /// Same as `send_record`, but fails right away when the queue is full
+    pub(crate) fn try_send_record(
+        &self,
+        record: &Record<'_>,
+    ) -> Result<DeliveryToken, ProducerError> {
+        self.send_copied(record, 0)
+    }
+
+    fn send_copied(
+        &self,
+        record: &Record<'_>,
+        queue_full_timeout_ms: i32,
+    ) -> Result<DeliveryToken, ProducerError> {
+        let value = record.payload.map(|p| (p.as_ptr(), p.len()));
+        let (token, opaque) = DeliveryToken::new(record.opaque);
+        let flags = RD_KAFKA_MSG_F_COPY;
+        self.produce(record, value, flags, opaque, queue_full_timeout_ms).map(|_| token)
+    }
+
+    /// Same as `send_record`, but sends `payload` as the value without
+    /// copying it. `record.payload` must be unset, otherwise this fails with
+    /// `InvalidArg`. The payload is moved into the producer and dropped once
+    /// its delivery report is served. If the message cannot be enqueued, the
+    /// payload is handed back.
+    pub fn send_owned<P>(
+        &self,
+        record: &Record<'_>,
+        payload: P,
+    ) -> Result<DeliveryToken, (ProducerError, P)>
+    where
+        P: AsRef<[u8]> + Send + 'static,
+    {
+        if record.payload.is_some() {
+            let err = KafkaError::with_reason(
+                KafkaErrorCode::InvalidArg,
+                "record payload must be unset when sending an owned payload",
             );
-            if res == -1 {
-                if let Some(err) = super::get_error_str(rd_kafka_last_error()) {
-                    return Err(ProducerError::SendError(err));
-                }
-            }
+            return Err((ProducerError::SendError(err), payload));
+        }
 
-            std::mem::forget(payload);
+        // boxed so the bytes stay put however `P` stores them
+        let payload = Box::new(payload);
+        let value = {
+            let p = (*payload).as_ref();
+            (p.as_ptr(), p.len())
+        };
+        let (token, opaque) = DeliveryToken::new(record.opaque);
+        token.hold(payload);
+
+        match self.produce(record, Some(value), 0, opaque, self.queue_full_timeout_ms) {
+            Ok(()) => Ok(token),
+            Err(err) => {
+                let payload = token.take_held().unwrap().downcast::<P>().unwrap();
+                Err((err, *payload))
+            }
         }
+    }
 
+    /// Enqueues the record with `value` as payload. On failure `opaque` is
+    /// released.
+    fn produce(
+        &self,
+        record: &Record<'_>,
+        value: Option<(*const u8, usize)>,
+        flags: u32,
+        opaque: *mut c_void,
+        queue_full_timeout_ms: i32,
+    ) -> Result<(), ProducerError> {
+        let (payload_ptr, payload_len) = match value {
+            Some((p, len)) => (p as *mut c_void, len),
+            None => (ptr::null_mut(), 0),
+        };
+        // librdkafka always takes its own copy of the key
+        let (key_ptr, key_len) = match record.key {
+            Some(k) => (k.as_ptr() as *const c_void, k.len()),
+            None => (ptr::null(), 0),
+        };
+
+        // ownership of the headers moves to librdkafka only on success
+        let hdrs = match record.headers {
+            Some(h) => h.to_native(),
+            None => ptr::null_mut(),
+        };
+
+        let timeout = Duration::from_millis(queue_full_timeout_ms.max(0) as u64);
+        let deadline = Instant::now() + timeout;
+        let produced = loop {
+            let produced = self.with_topic(record.topic, |rkt| unsafe {
+                let vus = [
+                    vu(rd_kafka_vtype_t_RD_KAFKA_VTYPE_RKT, VuValue { rkt }),
+                    vu(
+                        rd_kafka_vtype_t_RD_KAFKA_VTYPE_PARTITION,
+                        VuValue {
+                            i32: record.partition.unwrap_or(-1),
+                        },
+                    ),
+                    vu(
+                        rd_kafka_vtype_t_RD_KAFKA_VTYPE_MSGFLAGS,
+                        VuValue { i: flags as c_int },
+                    ),
+                    vu(
+                        rd_kafka_vtype_t_RD_KAFKA_VTYPE_VALUE,
+                        VuValue {
+                            mem: VuMem {
+                                ptr: payload_ptr,
+                                size: payload_len as u64,
+                            },
+                        },
+                    ),
+                    vu(
+                        rd_kafka_vtype_t_RD_KAFKA_VTYPE_KEY,
+                        VuValue {
+                            mem: VuMem {
+                                ptr: key_ptr as *mut c_void,
+                                size: key_len as u64,
+                            },
+                        },
+                    ),
+                    vu(rd_kafka_vtype_t_RD_KAFKA_VTYPE_HEADERS, VuValue { headers: hdrs }),
+                    // 0 lets librdkafka use the current time
+                    vu(
+                        rd_kafka_vtype_t_RD_KAFKA_VTYPE_TIMESTAMP,
+                        VuValue {
+                            i64: record.timestamp.unwrap_or(0),
+                        },
+                    ),
+                    vu(rd_kafka_vtype_t_RD_KAFKA_VTYPE_OPAQUE, VuValue { ptr: opaque }),
+                ];
+                rd_kafka_produceva(self.rk, vus.as_ptr(), vus.len() as u64)
+            });
+            let queue_full = match produced {
+                Ok(err) if !err.is_null() => unsafe {
+                    KafkaErrorCode::from(rd_kafka_error_code(err)) == KafkaErrorCode::QueueFull
+                },
+                _ => false,
+            };
+            // the topic lock is not held while waiting
+            if !queue_full || !self.wait_for_queue_space(deadline) {
+                break produced;
+            }
+            if let Ok(err) = produced {
+                unsafe { rd_kafka_error_destroy(err) };
+            }
+        };
+        // the error object carries the fatal, retriable and abort flags
+        let err = match produced {
+            Ok(err) if err.is_null() => None,
+            Ok(err) => Some(unsafe { KafkaError::from_native(err).resolve_fatal(self.rk) }),
+            Err(err) => Some(err),
+        };
+        if let Some(err) = err {
+            unsafe {
+                if !hdrs.is_null() {
+                    rd_kafka_headers_destroy(hdrs);
+                }
+                DeliveryToken::release(opaque);
+            }
+            return Err(ProducerError::SendError(err));
+        }
         Ok(())
     }
 
-    pub fn poll(&self) {
-        unsafe {
-            rd_kafka_poll(self.rk, 0);
+    /// Polls for at most `QUEUE_FULL_POLL_MS` so delivery reports can free
+    /// queue space. Returns false once `deadline` has passed.
+    fn wait_for_queue_space(&self, deadline: Instant) -> bool {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.as_millis() == 0 {
+            return false;
         }
+        // short polls, another thread may be serving the reports
+        let remaining_ms = remaining.as_millis().min(QUEUE_FULL_POLL_MS as u128);
+        self.poll_timeout(remaining_ms as i32);
+        true
+    }
+
+    pub fn poll(&self) {
+        self.poll_timeout(0);
+    }
+
+    pub(crate) fn poll_timeout(&self, timeout_ms: i32) -> i32 {
+        unsafe { rd_kafka_poll(self.rk, timeout_ms) }
+    }
+
+    /// Number of messages and requests waiting to be sent or acknowledged,
+    /// including delivery reports not served yet
+    pub fn in_flight_count(&self) -> i32 {
+        unsafe { rd_kafka_outq_len(self.rk) }
+    }
+
+    /// Returns the fatal error raised on this producer, if any. After a fatal
+    /// error every send fails and the producer has to be recreated.
+    pub fn fatal_error(&self) -> Option<KafkaError> {
+        unsafe { KafkaError::fatal(self.rk) }
     }
 
     pub fn flush(&self, timeout_ms: i32) {
@@ -84,10 +373,15 @@ impl Producer {
 impl Drop for Producer {
     fn drop(&mut self) {
         unsafe {
+            // fail whatever is still queued so pending delivery tokens resolve
+            rd_kafka_purge(
+                self.rk,
+                (RD_KAFKA_PURGE_F_QUEUE | RD_KAFKA_PURGE_F_INFLIGHT) as c_int,
+            );
+            rd_kafka_poll(self.rk, 0);
+
             // destroy producer topics
-            let keys: Vec<String> = self.rkt.keys().map(|s| s.to_string()).collect();
-            for key in keys {
-                let rkt = self.rkt.remove(&key).unwrap();
+            for (_, rkt) in self.rkt.get_mut().unwrap().drain() {
                 rd_kafka_topic_destroy(rkt);
             }
 
@@ -96,20 +390,36 @@ impl Drop for Producer {
     }
 }
 
+use std::fmt;
+impl fmt::Debug for Producer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Producer")
+            .field("rk", &self.rk)
+            .field("rkt", &self.rkt)
+            .field("queue_full_timeout_ms", &self.queue_full_timeout_ms)
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ProducerError {
-    UnregisteredTopic,
-    SendError(String),
+    SendError(KafkaError),
+    DeliveryError(KafkaError),
 }
 
-use std::fmt;
 impl fmt::Display for ProducerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Kafka Producer Error")
     }
 }
 
-impl std::error::Error for ProducerError {}
+impl std::error::Error for ProducerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProducerError::SendError(err) | ProducerError::DeliveryError(err) => Some(err),
+        }
+    }
+}
 
 unsafe impl Send for Producer {}
 unsafe impl Sync for Producer {}
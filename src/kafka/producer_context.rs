@@ -0,0 +1,34 @@
+use super::error::{KafkaError, KafkaErrorCode};
+use crate::bindings::rd_kafka_t;
+use std::ffi::{c_void, CStr};
+use std::os::raw::{c_char, c_int};
+
+/// Hooks into producer events, called on the thread polling the producer.
+/// The same rules as for `ConsumerContext` apply.
+pub trait ProducerContext: Send + Sync {
+    /// Called for client-level errors, e.g. brokers going down. Most are
+    /// transient and recovered from by librdkafka; fatal ones leave the
+    /// producer unusable, see `Producer::fatal_error`.
+    fn error(&self, _error: &KafkaError) {}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DefaultProducerContext;
+
+impl ProducerContext for DefaultProducerContext {}
+
+/// `error_cb` registered on producers built from `Config`
+pub(crate) unsafe extern "C" fn producer_error_cb(
+    rk: *mut rd_kafka_t,
+    err: c_int,
+    reason: *const c_char,
+    opaque: *mut c_void,
+) {
+    let context = &*(opaque as *const Box<dyn ProducerContext>);
+    let reason = CStr::from_ptr(reason).to_string_lossy();
+    let mut err = KafkaError::with_reason(err.into(), &reason);
+    if err.code() == KafkaErrorCode::Fatal {
+        err = err.resolve_fatal(rk);
+    }
+    super::call_hook(|| context.error(&err));
+}
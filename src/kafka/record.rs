@@ -0,0 +1,122 @@
+use super::headers::Headers;
+
+/// Message to produce, borrowing its data. Everything is copied when the
+/// record is sent.
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    pub topic: &'a str,
+    /// `None` produces a null value, e.g. a tombstone on compacted topics
+    pub payload: Option<&'a [u8]>,
+    pub key: Option<&'a [u8]>,
+    pub headers: Option<&'a Headers>,
+    /// Target partition, `None` lets the partitioner decide
+    pub partition: Option<i32>,
+    /// Create time in milliseconds since the epoch, `None` uses the current time
+    pub timestamp: Option<i64>,
+    /// Caller value carried along to the `DeliveryToken`
+    pub opaque: Option<usize>,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(topic: &'a str) -> Record<'a> {
+        Record {
+            topic,
+            payload: None,
+            key: None,
+            headers: None,
+            partition: None,
+            timestamp: None,
+            opaque: None,
+        }
+    }
+
+    pub fn with_payload(mut self, payload: &'a [u8]) -> Record<'a> {
+        self.payload = Some(payload);
+        self
+    }
+
+    pub fn with_key(mut self, key: &'a [u8]) -> Record<'a> {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn with_headers(mut self, headers: &'a Headers) -> Record<'a> {
+        self.headers = Some(headers);
+        self
+    }
+
+    pub fn with_partition(mut self, partition: i32) -> Record<'a> {
+        self.partition = Some(partition);
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp_ms: i64) -> Record<'a> {
+        self.timestamp = Some(timestamp_ms);
+        self
+    }
+
+    pub fn with_opaque(mut self, opaque: usize) -> Record<'a> {
+        self.opaque = Some(opaque);
+        self
+    }
+}
+
+/// Message to produce, owning all of its data. It is handed back to the
+/// caller when producing fails, so it can be retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedRecord {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub key: Option<Vec<u8>>,
+    pub headers: Option<Headers>,
+    /// Target partition, `None` lets the partitioner decide
+    pub partition: Option<i32>,
+    /// Create time in milliseconds since the epoch, `None` uses the current time
+    pub timestamp: Option<i64>,
+}
+
+impl OwnedRecord {
+    pub fn new(topic: &str, payload: Vec<u8>) -> OwnedRecord {
+        OwnedRecord {
+            topic: topic.to_string(),
+            payload,
+            key: None,
+            headers: None,
+            partition: None,
+            timestamp: None,
+        }
+    }
+
+    /// Borrows the record for sending
+    pub fn as_record(&self) -> Record<'_> {
+        Record {
+            topic: &self.topic,
+            payload: Some(&self.payload),
+            key: self.key.as_deref(),
+            headers: self.headers.as_ref(),
+            partition: self.partition,
+            timestamp: self.timestamp,
+            opaque: None,
+        }
+    }
+
+    pub fn with_key(mut self, key: Vec<u8>) -> OwnedRecord {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn with_headers(mut self, headers: Headers) -> OwnedRecord {
+        self.headers = Some(headers);
+        self
+    }
+
+    pub fn with_partition(mut self, partition: i32) -> OwnedRecord {
+        self.partition = Some(partition);
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp_ms: i64) -> OwnedRecord {
+        self.timestamp = Some(timestamp_ms);
+        self
+    }
+}
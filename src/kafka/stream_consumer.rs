@@ -0,0 +1,97 @@
+use super::consumer::{Consumer, ConsumerError};
+use super::error::{KafkaError, KafkaErrorCode};
+use super::message::{Message, MessageError};
+use crate::bindings::{rd_kafka_queue_cb_event_enable, rd_kafka_t};
+
+use futures_core::Stream;
+use std::ffi::c_void;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+type WakerSlot = Mutex<Option<Waker>>;
+
+/// Consumer exposed as an async `Stream` of messages.
+///
+/// librdkafka calls back into the stream whenever the consumer queue gets
+/// new events, which wakes the task awaiting the next message. No thread
+/// is spent polling in the background.
+pub struct StreamConsumer {
+    consumer: Consumer,
+    // referenced by the queue event callback until it is disabled on drop
+    waker: Arc<WakerSlot>,
+}
+
+impl StreamConsumer {
+    pub fn new(consumer: Consumer) -> Result<StreamConsumer, ConsumerError> {
+        let queue = consumer.queue();
+        if queue.is_null() {
+            let err = KafkaError::new(KafkaErrorCode::UnknownGroup);
+            return Err(ConsumerError::ConsumeError(err));
+        }
+
+        let waker = Arc::new(Mutex::new(None));
+        unsafe {
+            let opaque = Arc::into_raw(waker.clone()) as *mut c_void;
+            rd_kafka_queue_cb_event_enable(queue, Some(queue_event_cb), opaque);
+        }
+        Ok(StreamConsumer { consumer, waker })
+    }
+
+    fn poll_now(&self) -> Option<Result<Message, KafkaError>> {
+        self.consumer.poll(0).map(|res| match res {
+            Ok(msg) => Ok(msg.detach()),
+            Err(MessageError::KafkaError(err)) => Err(err),
+        })
+    }
+}
+
+impl Stream for StreamConsumer {
+    type Item = Result<Message, KafkaError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(res) = self.poll_now() {
+            return Poll::Ready(Some(res));
+        }
+
+        // register before polling again, so an event arriving in between
+        // is not missed
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+        match self.poll_now() {
+            Some(res) => Poll::Ready(Some(res)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Deref for StreamConsumer {
+    type Target = Consumer;
+
+    fn deref(&self) -> &Consumer {
+        &self.consumer
+    }
+}
+
+impl DerefMut for StreamConsumer {
+    fn deref_mut(&mut self) -> &mut Consumer {
+        &mut self.consumer
+    }
+}
+
+impl Drop for StreamConsumer {
+    fn drop(&mut self) {
+        unsafe {
+            rd_kafka_queue_cb_event_enable(self.consumer.queue(), None, std::ptr::null_mut());
+            drop(Arc::from_raw(Arc::as_ptr(&self.waker)));
+        }
+    }
+}
+
+/// Queue event callback, runs on a librdkafka thread
+unsafe extern "C" fn queue_event_cb(_rk: *mut rd_kafka_t, opaque: *mut c_void) {
+    let waker = &*(opaque as *const WakerSlot);
+    if let Some(waker) = waker.lock().unwrap().take() {
+        waker.wake();
+    }
+}
@@ -0,0 +1,81 @@
+use super::producer::Producer;
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+const POLL_INTERVAL_MS: i32 = 100;
+const DEFAULT_FLUSH_TIMEOUT_MS: i32 = 10_000;
+
+/// Producer with a background thread polling it, so delivery reports and
+/// callbacks are served without the caller having to call `poll`.
+///
+/// Dropping it stops the thread and flushes outstanding messages.
+pub struct ThreadedProducer {
+    producer: Arc<Producer>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    flush_timeout_ms: i32,
+}
+
+impl ThreadedProducer {
+    pub fn new(producer: Producer) -> ThreadedProducer {
+        ThreadedProducer::with_poll_callback(producer, |_| {})
+    }
+
+    /// Same as `new`, but calls `on_poll` on the poll thread with the number
+    /// of events served by every poll.
+    pub(crate) fn with_poll_callback<F>(producer: Producer, on_poll: F) -> ThreadedProducer
+    where
+        F: Fn(i32) + Send + 'static,
+    {
+        let producer = Arc::new(producer);
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread_producer = producer.clone();
+        let thread_shutdown = shutdown.clone();
+        let handle = thread::Builder::new()
+            .name(String::from("producer_poll"))
+            .spawn(move || {
+                while !thread_shutdown.load(Ordering::Relaxed) {
+                    on_poll(thread_producer.poll_timeout(POLL_INTERVAL_MS));
+                }
+            })
+            .expect("Error spawning producer poll thread");
+
+        ThreadedProducer {
+            producer,
+            shutdown,
+            handle: Some(handle),
+            flush_timeout_ms: DEFAULT_FLUSH_TIMEOUT_MS,
+        }
+    }
+
+    /// How long dropping the producer may block flushing outstanding messages
+    pub fn set_flush_timeout(&mut self, timeout_ms: i32) -> &mut Self {
+        self.flush_timeout_ms = timeout_ms;
+        self
+    }
+}
+
+impl Deref for ThreadedProducer {
+    type Target = Producer;
+
+    fn deref(&self) -> &Producer {
+        &self.producer
+    }
+}
+
+impl Drop for ThreadedProducer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("Kafka producer poll thread panicked");
+            }
+        }
+
+        self.producer.flush(self.flush_timeout_ms);
+    }
+}
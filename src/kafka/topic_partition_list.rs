@@ -0,0 +1,221 @@
+use super::error::{KafkaError, KafkaErrorCode};
+use super::offset::Offset;
+use crate::bindings::{
+    rd_kafka_topic_partition_list_add, rd_kafka_topic_partition_list_add_range,
+    rd_kafka_topic_partition_list_copy, rd_kafka_topic_partition_list_del,
+    rd_kafka_topic_partition_list_destroy, rd_kafka_topic_partition_list_find,
+    rd_kafka_topic_partition_list_new, rd_kafka_topic_partition_list_set_offset,
+    rd_kafka_topic_partition_list_sort, rd_kafka_topic_partition_list_t,
+    rd_kafka_topic_partition_t,
+};
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+
+/// Snapshot of a single list entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicPartition {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: Offset,
+    /// Per-entry result set by librdkafka, e.g. after a commit
+    pub error: Option<KafkaError>,
+}
+
+impl TopicPartition {
+    unsafe fn from_native(elem: &rd_kafka_topic_partition_t) -> TopicPartition {
+        TopicPartition {
+            topic: CStr::from_ptr(elem.topic).to_string_lossy().to_string(),
+            partition: elem.partition,
+            offset: Offset::from_raw(elem.offset),
+            error: super::get_error(elem.err),
+        }
+    }
+}
+
+/// Owned list of (topic, partition, offset) entries
+#[derive(Debug)]
+pub struct TopicPartitionList {
+    ptr: *mut rd_kafka_topic_partition_list_t,
+}
+
+impl TopicPartitionList {
+    pub fn new() -> TopicPartitionList {
+        TopicPartitionList::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> TopicPartitionList {
+        unsafe { TopicPartitionList::from_ptr(rd_kafka_topic_partition_list_new(capacity as i32)) }
+    }
+
+    /// Takes ownership of a native list
+    pub(crate) unsafe fn from_ptr(ptr: *mut rd_kafka_topic_partition_list_t) -> TopicPartitionList {
+        TopicPartitionList { ptr }
+    }
+
+    pub(crate) fn ptr(&self) -> *mut rd_kafka_topic_partition_list_t {
+        self.ptr
+    }
+
+    /// Adds an entry. Fails with `InvalidArg` if `topic` contains a NUL byte.
+    pub fn add(&mut self, topic: &str, partition: i32) -> Result<&mut Self, KafkaError> {
+        let topic = topic_name(topic)?;
+        unsafe {
+            rd_kafka_topic_partition_list_add(self.ptr, topic.as_ptr(), partition);
+        }
+        Ok(self)
+    }
+
+    /// Adds partitions `start..=stop` of `topic`
+    pub fn add_range(
+        &mut self,
+        topic: &str,
+        start: i32,
+        stop: i32,
+    ) -> Result<&mut Self, KafkaError> {
+        let topic = topic_name(topic)?;
+        unsafe {
+            rd_kafka_topic_partition_list_add_range(self.ptr, topic.as_ptr(), start, stop);
+        }
+        Ok(self)
+    }
+
+    /// Sets the offset of an entry, adding it first if needed
+    pub fn set_offset(
+        &mut self,
+        topic: &str,
+        partition: i32,
+        offset: Offset,
+    ) -> Result<&mut Self, KafkaError> {
+        let topic = topic_name(topic)?;
+        let offset = offset.to_raw();
+        unsafe {
+            // only fails when the entry is missing
+            if rd_kafka_topic_partition_list_set_offset(self.ptr, topic.as_ptr(), partition, offset)
+                != 0
+            {
+                let elem = rd_kafka_topic_partition_list_add(self.ptr, topic.as_ptr(), partition);
+                (*elem).offset = offset;
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn find(&self, topic: &str, partition: i32) -> Option<TopicPartition> {
+        let topic = topic_name(topic).ok()?;
+        unsafe {
+            let elem = rd_kafka_topic_partition_list_find(self.ptr, topic.as_ptr(), partition);
+            match elem.is_null() {
+                true => None,
+                false => Some(TopicPartition::from_native(&*elem)),
+            }
+        }
+    }
+
+    /// Removes an entry, returning whether it was present
+    pub fn delete(&mut self, topic: &str, partition: i32) -> bool {
+        let topic = match topic_name(topic) {
+            Ok(topic) => topic,
+            Err(_) => return false,
+        };
+        unsafe { rd_kafka_topic_partition_list_del(self.ptr, topic.as_ptr(), partition) != 0 }
+    }
+
+    /// Sorts the entries by topic, then partition
+    pub fn sort(&mut self) {
+        unsafe { rd_kafka_topic_partition_list_sort(self.ptr, None, std::ptr::null_mut()) }
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { (*self.ptr).cnt as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { list: self, idx: 0 }
+    }
+
+    /// First per-entry error, if any entry failed
+    pub fn first_error(&self) -> Option<KafkaError> {
+        self.iter().find_map(|e| e.error)
+    }
+
+    fn elem(&self, idx: usize) -> Option<&rd_kafka_topic_partition_t> {
+        if idx >= self.len() {
+            return None;
+        }
+        unsafe { Some(&*(*self.ptr).elems.add(idx)) }
+    }
+}
+
+/// Topic names with an interior NUL cannot be passed to librdkafka
+fn topic_name(topic: &str) -> Result<CString, KafkaError> {
+    CString::new(topic).map_err(|_| KafkaError::new(KafkaErrorCode::InvalidArg))
+}
+
+impl Default for TopicPartitionList {
+    fn default() -> TopicPartitionList {
+        TopicPartitionList::new()
+    }
+}
+
+impl Clone for TopicPartitionList {
+    fn clone(&self) -> TopicPartitionList {
+        unsafe { TopicPartitionList::from_ptr(rd_kafka_topic_partition_list_copy(self.ptr)) }
+    }
+}
+
+impl Drop for TopicPartitionList {
+    fn drop(&mut self) {
+        unsafe { rd_kafka_topic_partition_list_destroy(self.ptr) }
+    }
+}
+
+pub struct Iter<'a> {
+    list: &'a TopicPartitionList,
+    idx: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = TopicPartition;
+
+    fn next(&mut self) -> Option<TopicPartition> {
+        let elem = self.list.elem(self.idx)?;
+        self.idx += 1;
+        unsafe { Some(TopicPartition::from_native(elem)) }
+    }
+}
+
+impl<'a> IntoIterator for &'a TopicPartitionList {
+    type Item = TopicPartition;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl TryFrom<Vec<(String, i32, Offset)>> for TopicPartitionList {
+    type Error = KafkaError;
+
+    fn try_from(entries: Vec<(String, i32, Offset)>) -> Result<TopicPartitionList, KafkaError> {
+        let mut list = TopicPartitionList::with_capacity(entries.len());
+        for (topic, partition, offset) in entries {
+            list.set_offset(&topic, partition, offset)?;
+        }
+        Ok(list)
+    }
+}
+
+impl From<&TopicPartitionList> for Vec<(String, i32, Offset)> {
+    fn from(list: &TopicPartitionList) -> Vec<(String, i32, Offset)> {
+        list.iter()
+            .map(|e| (e.topic, e.partition, e.offset))
+            .collect()
+    }
+}
+
+unsafe impl Send for TopicPartitionList {}
+unsafe impl Sync for TopicPartitionList {}